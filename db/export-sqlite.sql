@@ -0,0 +1,63 @@
+-- schema of the SQLite database written by the export
+
+CREATE TABLE sites
+( id INTEGER NOT NULL PRIMARY KEY
+, name TEXT NOT NULL UNIQUE
+, url TEXT NOT NULL UNIQUE
+, css_class TEXT NOT NULL
+, variant TEXT NOT NULL
+, notes TEXT NOT NULL
+, available INTEGER NOT NULL
+, max_attempts INTEGER NULL
+, streak_max_gap_days INTEGER NULL
+);
+
+CREATE TABLE players
+( id INTEGER NOT NULL PRIMARY KEY
+, name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE puzzles
+( id INTEGER NOT NULL PRIMARY KEY
+, site_id INTEGER NOT NULL REFERENCES sites(id)
+, player_id INTEGER NOT NULL REFERENCES players(id)
+, puzzle_date TEXT NOT NULL
+, day_ordinal INTEGER NOT NULL
+, head TEXT NOT NULL
+, tail TEXT NOT NULL
+, pattern TEXT NOT NULL
+, solution TEXT NOT NULL
+, attempts INTEGER NULL
+, raw_pattern TEXT NULL
+, UNIQUE (site_id, player_id, puzzle_date, day_ordinal)
+);
+
+CREATE VIEW sites_and_puzzles AS
+    SELECT
+        s.id site_id,
+        s.name site_name,
+        s.url site_url,
+        s.css_class site_css_class,
+        s.variant,
+        s.notes,
+        s.available,
+        s.max_attempts,
+        s.streak_max_gap_days,
+        p.id puzzle_id,
+        p.puzzle_date,
+        p.day_ordinal,
+        p.head,
+        p.tail,
+        p.pattern,
+        p.solution,
+        p.attempts,
+        p.raw_pattern,
+        p.player_id,
+        pl.name player_name
+    FROM
+        sites s
+        INNER JOIN puzzles p
+            ON p.site_id = s.id
+        INNER JOIN players pl
+            ON pl.id = p.player_id
+;
@@ -0,0 +1,512 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use chrono::{Local, NaiveDate};
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, Response};
+use hyper::body::{Bytes, Incoming};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::{
+    db_puzzle_to_puzzle_part, default_player_name, get_query_pairs, get_spoil_flags, has_player_scope,
+    return_500,
+};
+use crate::database::{DbConnection, OptionResult, StoreResult};
+use crate::auth::get_request_token;
+use crate::config::TokenScope;
+use crate::model::{Player, Puzzle, PuzzleSite, SiteAndPuzzle};
+use crate::nyt::{find_nyt_site, import_games, NytDump, NytImportOutcome};
+use crate::parsers::parse_puzzle_data;
+use crate::stats::{is_victory, load_stats, StatsWindow};
+
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiError {
+    pub error: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")] pub message: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiDate {
+    pub date: NaiveDate,
+    pub allow_spoiling: bool,
+    pub spoil: bool,
+    pub puzzles: Vec<ApiPuzzle>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiSinglePuzzle {
+    pub allow_spoiling: bool,
+    pub spoil: bool,
+    pub puzzle: ApiPuzzle,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiPuzzle {
+    pub id: i64,
+    pub site: PuzzleSite,
+    pub date: NaiveDate,
+    pub day_ordinal: i64,
+    pub head: String,
+    pub tail: String,
+    pub raw_pattern: Option<String>,
+    pub attempts: Option<i64>,
+    pub sub_puzzles: Vec<ApiSubPuzzle>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiSubPuzzle {
+    pub victory: bool,
+    pub pattern_lines: Vec<String>,
+    pub guess_lines: Vec<ApiGuessLine>,
+    // the following are only output if spoilers are enabled
+    #[serde(skip_serializing_if = "Option::is_none")] pub solution: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] pub solution_lines: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiGuessLine {
+    pub pattern: String,
+    // only output if spoilers are enabled
+    #[serde(skip_serializing_if = "Option::is_none")] pub guess: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(untagged)]
+enum ApiSiteReference {
+    Id(i64),
+    Name(String),
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(untagged)]
+enum ApiDayOrdinal {
+    Number(i64),
+    /// Only `"auto"` (or an empty string) is valid; assigns the next free daily ordinal.
+    Keyword(String),
+}
+impl Default for ApiDayOrdinal {
+    fn default() -> Self {
+        Self::Keyword("auto".to_owned())
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+struct ApiPopulateRequest {
+    pub site: ApiSiteReference,
+    pub result: String,
+    pub solution: String,
+    #[serde(default)] pub day_ordinal: ApiDayOrdinal,
+    #[serde(default)] pub date: Option<NaiveDate>,
+    #[serde(default)] pub overwrite: bool,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiDuplicatePuzzle {
+    pub error: &'static str,
+    pub existing_puzzle_id: i64,
+    pub next_free_day_ordinal: i64,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiPopulateSuccess {
+    pub puzzle_id: i64,
+    pub day_ordinal: i64,
+    pub attempts: Option<i64>,
+    pub victory: bool,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct ApiImportResult {
+    pub games: Vec<NytImportOutcome>,
+}
+
+
+fn return_json<T: Serialize>(value: &T, status: u16) -> Result<Response<Full<Bytes>>, Infallible> {
+    let body_string = match serde_json::to_string(value) {
+        Ok(bs) => bs,
+        Err(e) => {
+            error!("failed to serialize JSON response: {}", e);
+            return return_500();
+        },
+    };
+    let response_res = Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(body_string)));
+    match response_res {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("failed to assemble JSON response: {}", e);
+            return_500()
+        },
+    }
+}
+
+fn return_json_error(error: &'static str, status: u16) -> Result<Response<Full<Bytes>>, Infallible> {
+    return_json(&ApiError { error, message: None }, status)
+}
+
+fn return_json_error_message<M: Into<String>>(error: &'static str, message: M, status: u16) -> Result<Response<Full<Bytes>>, Infallible> {
+    return_json(&ApiError { error, message: Some(message.into()) }, status)
+}
+
+/// Looks up the player named in the `player` query parameter, falling back to the default player.
+async fn find_api_player(db_conn: &DbConnection, query_pairs: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> OptionResult<Player> {
+    let player_name = match query_pairs.get("player") {
+        Some(pn) => pn.clone().into_owned(),
+        None => default_player_name().await,
+    };
+    db_conn.get_player_by_name(&player_name).await
+}
+
+fn site_and_puzzle_to_api(db_puzzle: &SiteAndPuzzle, spoil: bool) -> ApiPuzzle {
+    let puzzle_part = db_puzzle_to_puzzle_part(db_puzzle);
+    let sub_puzzles = puzzle_part.sub_puzzles
+        .into_iter()
+        .map(|sp| {
+            let guess_lines = if puzzle_part.site.variant == "wordle32" {
+                // the guesses do not correspond to pattern lines
+                sp.pattern_lines.iter()
+                    .map(|p| ApiGuessLine { pattern: p.clone(), guess: None })
+                    .collect()
+            } else {
+                sp.guess_lines.into_iter()
+                    .map(|(p, g)| ApiGuessLine { pattern: p, guess: if spoil { Some(g) } else { None } })
+                    .collect()
+            };
+            ApiSubPuzzle {
+                victory: sp.victory,
+                pattern_lines: sp.pattern_lines,
+                guess_lines,
+                solution: if spoil { Some(sp.solution) } else { None },
+                solution_lines: if spoil { Some(sp.solution_lines) } else { None },
+            }
+        })
+        .collect();
+
+    ApiPuzzle {
+        id: db_puzzle.puzzle.id,
+        site: puzzle_part.site,
+        date: db_puzzle.puzzle.date,
+        day_ordinal: db_puzzle.puzzle.day_ordinal,
+        head: puzzle_part.head,
+        tail: puzzle_part.tail,
+        raw_pattern: puzzle_part.raw_guesses,
+        attempts: puzzle_part.attempts,
+        sub_puzzles,
+    }
+}
+
+pub(crate) async fn handle_api(
+    req: Request<Incoming>,
+    path_segs: &[String],
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if path_segs.len() < 2 || path_segs[0] != "v1" {
+        return return_json_error("not_found", 404);
+    }
+
+    if path_segs.len() == 2 && path_segs[1] == "populate" {
+        if req.method() != Method::POST {
+            return return_json_error("method_not_allowed", 405);
+        }
+        return handle_api_populate(req).await;
+    }
+
+    if path_segs.len() == 3 && path_segs[1] == "import" && path_segs[2] == "nyt-wordle" {
+        if req.method() != Method::POST {
+            return return_json_error("method_not_allowed", 405);
+        }
+        return handle_api_import_nyt(req).await;
+    }
+
+    if req.method() != Method::GET {
+        return return_json_error("method_not_allowed", 405);
+    }
+
+    if path_segs.len() == 3 && path_segs[1] == "dates" {
+        handle_api_date(req, &path_segs[2]).await
+    } else if path_segs.len() == 3 && path_segs[1] == "puzzles" {
+        handle_api_puzzle(req, &path_segs[2]).await
+    } else if path_segs.len() == 2 && path_segs[1] == "sites" {
+        handle_api_sites(req).await
+    } else if path_segs.len() == 2 && path_segs[1] == "players" {
+        handle_api_players(req).await
+    } else if path_segs.len() == 2 && path_segs[1] == "stats" {
+        handle_api_stats(req).await
+    } else {
+        return_json_error("not_found", 404)
+    }
+}
+
+async fn handle_api_date(req: Request<Incoming>, date_string: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    let date = match NaiveDate::parse_from_str(date_string, "%Y-%m-%d") {
+        Ok(d) => d,
+        Err(_) => return return_json_error("not_found", 404),
+    };
+
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    let (allow_spoiling, spoil) = get_spoil_flags(&query_pairs, token.as_ref(), &date).await;
+
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match find_api_player(&db_conn, &query_pairs).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_json_error("player_not_found", 404),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let db_puzzles = match db_conn.get_puzzles_on_date(player.id, date).await {
+        Some(ps) => ps,
+        None => return return_500(), // error already logged
+    };
+
+    let puzzles = db_puzzles.iter()
+        .map(|p| site_and_puzzle_to_api(p, spoil))
+        .collect();
+    let api_date = ApiDate {
+        date,
+        allow_spoiling,
+        spoil,
+        puzzles,
+    };
+    return_json(&api_date, 200)
+}
+
+async fn handle_api_puzzle(req: Request<Incoming>, id_string: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    let id: i64 = match id_string.parse() {
+        Ok(i) => i,
+        Err(_) => return return_json_error("not_found", 404),
+    };
+
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let db_puzzle = match db_conn.get_puzzle_by_id(id).await {
+        OptionResult::Present(d) => d,
+        OptionResult::Absent => return return_json_error("not_found", 404),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    let (allow_spoiling, spoil) = get_spoil_flags(&query_pairs, token.as_ref(), &db_puzzle.puzzle.date).await;
+
+    let single_puzzle = ApiSinglePuzzle {
+        allow_spoiling,
+        spoil,
+        puzzle: site_and_puzzle_to_api(&db_puzzle, spoil),
+    };
+    return_json(&single_puzzle, 200)
+}
+
+async fn handle_api_sites(_req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return return_500(), // error already logged
+    };
+    return_json(&sites, 200)
+}
+
+async fn handle_api_players(_req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let players = match db_conn.get_players().await {
+        Some(p) => p,
+        None => return return_500(), // error already logged
+    };
+    return_json(&players, 200)
+}
+
+async fn handle_api_stats(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let query_pairs = get_query_pairs(req.uri());
+    let today = Local::now().naive_local().date();
+    let window = match StatsWindow::from_query(&query_pairs, today) {
+        Ok(w) => w,
+        Err(e) => return return_json_error_message("invalid_request", e, 400),
+    };
+
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match find_api_player(&db_conn, &query_pairs).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_json_error("player_not_found", 404),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let stats = match load_stats(&db_conn, player.id, &window, today).await {
+        Some(s) => s,
+        None => return return_500(), // error already logged
+    };
+    return_json(&stats, 200)
+}
+
+async fn handle_api_populate(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match find_api_player(&db_conn, &query_pairs).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_json_error("player_not_found", 404),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    if !has_player_scope(token.as_ref(), TokenScope::Write, &player.name).await {
+        return return_json_error("forbidden", 403);
+    }
+
+    let (_head, body) = req.into_parts();
+    let body_bytes = match body.collect().await {
+        Ok(bs) => bs.to_bytes(),
+        Err(e) => {
+            error!("failed to assemble body bytes: {}", e);
+            return return_500();
+        },
+    };
+    let populate_request: ApiPopulateRequest = match serde_json::from_slice(&body_bytes) {
+        Ok(pr) => pr,
+        Err(e) => return return_json_error_message("invalid_request", e.to_string(), 400),
+    };
+
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return return_500(), // error already logged
+    };
+    let site_opt = match &populate_request.site {
+        ApiSiteReference::Id(id) => sites.iter().find(|s| s.id == *id),
+        ApiSiteReference::Name(name) => sites.iter().find(|s| &s.name == name),
+    };
+    let site = match site_opt {
+        Some(s) => s,
+        None => return return_json_error("site_not_found", 400),
+    };
+
+    let result = populate_request.result.replace('\r', "");
+    let raw_solution = populate_request.solution.replace('\r', "");
+    let puzzle_data = match parse_puzzle_data(site, &result, &raw_solution) {
+        Ok(pd) => pd,
+        Err(e) => return return_json(&e, 400),
+    };
+
+    let today = Local::now().naive_local().date();
+    let date = populate_request.date.unwrap_or(today);
+    if date > today {
+        return return_json_error("date_in_future", 400);
+    }
+    let day_ordinal = match &populate_request.day_ordinal {
+        ApiDayOrdinal::Number(dord) => *dord,
+        ApiDayOrdinal::Keyword(kw) if kw == "auto" || kw.is_empty() => match db_conn.get_next_free_day_ordinal(site.id, player.id, date, None).await {
+            Some(dord) => dord,
+            None => return return_500(), // error already logged
+        },
+        ApiDayOrdinal::Keyword(_) => return return_json_error_message(
+            "invalid_request",
+            "day_ordinal must be a number or \"auto\"",
+            400,
+        ),
+    };
+    let puzzle = puzzle_data.into_puzzle(site.id, player.id, date, day_ordinal);
+    let puzzle_id = if populate_request.overwrite {
+        match db_conn.store_or_overwrite_puzzle(&puzzle).await {
+            Some(pid) => pid,
+            None => return return_500(), // error already logged
+        }
+    } else {
+        match db_conn.store_puzzle(&puzzle).await {
+            StoreResult::Stored(pid) => pid,
+            StoreResult::Duplicate => return return_api_duplicate(&db_conn, &puzzle).await,
+            StoreResult::Error => return return_500(), // error already logged
+        }
+    };
+
+    let success = ApiPopulateSuccess {
+        puzzle_id,
+        day_ordinal: puzzle.day_ordinal,
+        attempts: puzzle.attempts,
+        victory: is_victory(puzzle.attempts, site.max_attempts),
+    };
+    return_json(&success, 200)
+}
+
+async fn handle_api_import_nyt(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match find_api_player(&db_conn, &query_pairs).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_json_error("player_not_found", 404),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    if !has_player_scope(token.as_ref(), TokenScope::Write, &player.name).await {
+        return return_json_error("forbidden", 403);
+    }
+    let site_name_opt = query_pairs.get("site")
+        .map(|s| s.clone().into_owned());
+
+    let (_head, body) = req.into_parts();
+    let body_bytes = match body.collect().await {
+        Ok(bs) => bs.to_bytes(),
+        Err(e) => {
+            error!("failed to assemble body bytes: {}", e);
+            return return_500();
+        },
+    };
+    let dump: NytDump = match serde_json::from_slice(&body_bytes) {
+        Ok(d) => d,
+        Err(e) => return return_json_error_message("invalid_request", e.to_string(), 400),
+    };
+
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return return_500(), // error already logged
+    };
+    let site = match find_nyt_site(&sites, site_name_opt.as_deref()) {
+        Some(s) => s,
+        None => return return_json_error("site_not_found", 400),
+    };
+
+    let outcomes = import_games(&db_conn, site, &player, &dump.into_games()).await;
+    return_json(&ApiImportResult { games: outcomes }, 200)
+}
+
+async fn return_api_duplicate(db_conn: &DbConnection, puzzle: &Puzzle) -> Result<Response<Full<Bytes>>, Infallible> {
+    let existing_puzzle_id = match db_conn.get_puzzle_id_for_day(puzzle.site_id, puzzle.player_id, puzzle.date, puzzle.day_ordinal).await {
+        OptionResult::Present(pid) => pid,
+        OptionResult::Absent => {
+            error!(
+                "puzzle for site {} and player {} on {} with daily ordinal {} is a duplicate but cannot be found",
+                puzzle.site_id, puzzle.player_id, puzzle.date, puzzle.day_ordinal,
+            );
+            return return_500();
+        },
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let next_free_day_ordinal = match db_conn.get_next_free_day_ordinal(puzzle.site_id, puzzle.player_id, puzzle.date, None).await {
+        Some(dord) => dord,
+        None => return return_500(), // error already logged
+    };
+
+    let duplicate = ApiDuplicatePuzzle {
+        error: "duplicate_puzzle",
+        existing_puzzle_id,
+        next_free_day_ordinal,
+    };
+    return_json(&duplicate, 409)
+}
@@ -0,0 +1,330 @@
+//! Obtaining the access token of a request, which may be passed in the `Authorization` header, via
+//! a session cookie obtained by logging in, or in the `token` query parameter, and verifying it
+//! against the configured tokens, which may be stored as salted hashes.
+
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use hyper::HeaderMap;
+use once_cell::sync::OnceCell;
+use rand::{RngCore, thread_rng};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+use tracing::info;
+
+use crate::config::{AccessToken, CONFIG};
+
+
+/// The name of the cookie containing the session.
+pub(crate) const SESSION_COOKIE_NAME: &str = "wordle_archive_session";
+
+/// The prefix of token hashes, naming the hash algorithm.
+const TOKEN_HASH_ALGORITHM: &str = "hmac-sha256";
+
+/// The length of the salt of token hashes, in bytes.
+const TOKEN_HASH_SALT_LENGTH: usize = 16;
+
+/// The secret used to sign sessions if none has been configured.
+static GENERATED_SESSION_SECRET: OnceCell<Vec<u8>> = OnceCell::new();
+
+
+/// Returns the digest identifying a configured token within a session, so that the session cookie
+/// contains neither the token itself nor its stored hash.
+fn token_digest(access_token: &AccessToken) -> String {
+    let stored = access_token.token_hash()
+        .or(access_token.token())
+        .unwrap_or("");
+    URL_SAFE_NO_PAD.encode(Sha256::digest(stored.as_bytes()))
+}
+
+fn new_mac(secret: &[u8]) -> Hmac<Sha256> {
+    // HMAC accepts keys of any length
+    Hmac::<Sha256>::new_from_slice(secret).expect("HMAC rejected key")
+}
+
+/// Hashes the token with a randomly generated salt. The result is stored as `token_hash` in the
+/// configuration.
+///
+/// Since a single iteration of HMAC-SHA256 is used, tokens should be long and random rather than
+/// chosen by hand.
+pub(crate) fn hash_token(token: &str) -> String {
+    let mut salt = [0u8; TOKEN_HASH_SALT_LENGTH];
+    thread_rng().fill_bytes(&mut salt);
+    hash_token_with_salt(token, &salt)
+}
+
+fn hash_token_with_salt(token: &str, salt: &[u8]) -> String {
+    let mut mac = new_mac(salt);
+    mac.update(token.as_bytes());
+    format!(
+        "{}${}${}",
+        TOKEN_HASH_ALGORITHM, URL_SAFE_NO_PAD.encode(salt), URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()),
+    )
+}
+
+/// Splits a token hash into its salt and its digest.
+fn parse_token_hash(token_hash: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut pieces = token_hash.split('$');
+    let algorithm = pieces.next()?;
+    let salt_b64 = pieces.next()?;
+    let digest_b64 = pieces.next()?;
+    if algorithm != TOKEN_HASH_ALGORITHM || pieces.next().is_some() {
+        return None;
+    }
+    let salt = URL_SAFE_NO_PAD.decode(salt_b64).ok()?;
+    let digest = URL_SAFE_NO_PAD.decode(digest_b64).ok()?;
+    Some((salt, digest))
+}
+
+/// Returns whether the token hash has the format output by [`hash_token`].
+pub(crate) fn is_valid_token_hash(token_hash: &str) -> bool {
+    parse_token_hash(token_hash).is_some()
+}
+
+/// Returns whether the given token is the configured one, comparing in constant time.
+pub(crate) fn token_matches(access_token: &AccessToken, token: &str) -> bool {
+    if let Some(token_hash) = access_token.token_hash() {
+        let (salt, digest) = match parse_token_hash(token_hash) {
+            Some(sd) => sd,
+            None => return false,
+        };
+        let mut mac = new_mac(&salt);
+        mac.update(token.as_bytes());
+        mac.verify_slice(&digest).is_ok()
+    } else if let Some(plain_token) = access_token.token() {
+        plain_token.as_bytes().ct_eq(token.as_bytes()).into()
+    } else {
+        false
+    }
+}
+
+/// Returns the configured access token matching the given token.
+pub(crate) async fn find_access_token(token: &str) -> Option<AccessToken> {
+    let config_guard = CONFIG
+        .get().expect("CONFIG not set")
+        .read().await;
+    config_guard.tokens.iter()
+        .find(|t| token_matches(t, token))
+        .cloned()
+}
+
+/// Returns the value of a session cookie for the given token, expiring at the given Unix time.
+///
+/// The value consists of the digest of the token, the expiry time and a signature of both.
+pub(crate) fn sign_session(secret: &[u8], access_token: &AccessToken, expires_at: i64) -> String {
+    let payload = format!("{}.{}", token_digest(access_token), expires_at);
+    let mut mac = new_mac(secret);
+    mac.update(payload.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    format!("{}.{}", payload, signature)
+}
+
+/// Verifies the value of a session cookie and returns the digest of its token if the signature
+/// is valid and the session has not expired at the given Unix time.
+pub(crate) fn verify_session(secret: &[u8], value: &str, now: i64) -> Option<String> {
+    let (payload, signature_b64) = value.rsplit_once('.')?;
+    let signature = URL_SAFE_NO_PAD.decode(signature_b64).ok()?;
+    let mut mac = new_mac(secret);
+    mac.update(payload.as_bytes());
+    mac.verify_slice(&signature).ok()?;
+
+    let (digest, expires_at_str) = payload.split_once('.')?;
+    let expires_at: i64 = expires_at_str.parse().ok()?;
+    if expires_at <= now {
+        return None;
+    }
+    Some(digest.to_owned())
+}
+
+/// Returns the secret with which sessions are signed: the configured one or, failing that, one
+/// generated randomly when it is first needed.
+async fn session_secret() -> Vec<u8> {
+    {
+        let config_guard = CONFIG
+            .get().expect("CONFIG not set")
+            .read().await;
+        if let Some(secret) = &config_guard.session_secret {
+            return secret.as_bytes().to_vec();
+        }
+    }
+    GENERATED_SESSION_SECRET.get_or_init(|| {
+        info!("no session_secret configured; sessions will end when the server is restarted");
+        let mut secret = vec![0u8; 32];
+        thread_rng().fill_bytes(&mut secret);
+        secret
+    }).clone()
+}
+
+/// Returns the `Set-Cookie` header value establishing a session for the given token.
+pub(crate) async fn session_cookie(access_token: &AccessToken) -> String {
+    let (lifetime_days, secure, path) = {
+        let config_guard = CONFIG
+            .get().expect("CONFIG not set")
+            .read().await;
+        (config_guard.session_lifetime_days, config_guard.secure_session_cookie, config_guard.base_path.clone())
+    };
+    let max_age = lifetime_days * 24 * 60 * 60;
+    let value = sign_session(&session_secret().await, access_token, Utc::now().timestamp() + max_age);
+    format!(
+        "{}={}; Max-Age={}; Path={}; HttpOnly; SameSite=Strict{}",
+        SESSION_COOKIE_NAME, value, max_age, path, if secure { "; Secure" } else { "" },
+    )
+}
+
+/// Returns the `Set-Cookie` header value ending the session.
+pub(crate) async fn session_removal_cookie() -> String {
+    let (secure, path) = {
+        let config_guard = CONFIG
+            .get().expect("CONFIG not set")
+            .read().await;
+        (config_guard.secure_session_cookie, config_guard.base_path.clone())
+    };
+    format!(
+        "{}=; Max-Age=0; Path={}; HttpOnly; SameSite=Strict{}",
+        SESSION_COOKIE_NAME, path, if secure { "; Secure" } else { "" },
+    )
+}
+
+/// Returns the value of the cookie with the given name.
+fn get_cookie<'h>(headers: &'h HeaderMap, name: &str) -> Option<&'h str> {
+    headers.get_all("Cookie").iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(key, _value)| *key == name)
+        .map(|(_key, value)| value)
+}
+
+/// Returns the token passed in the `Authorization: Bearer` header.
+fn get_bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get("Authorization")?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("Bearer") {
+        Some(token.trim())
+    } else {
+        None
+    }
+}
+
+/// Returns the configured access token matching the token passed with the request, in order of
+/// preference in the `Authorization: Bearer` header, via the session cookie or in the `token`
+/// query parameter.
+///
+/// A session is only honored if its token is still configured.
+pub(crate) async fn get_request_token(headers: &HeaderMap, query_pairs: &HashMap<Cow<'_, str>, Cow<'_, str>>) -> Option<AccessToken> {
+    if let Some(token) = get_bearer_token(headers) {
+        return find_access_token(token).await;
+    }
+
+    if let Some(session) = get_cookie(headers, SESSION_COOKIE_NAME) {
+        let secret = session_secret().await;
+        if let Some(digest) = verify_session(&secret, session, Utc::now().timestamp()) {
+            let config_guard = CONFIG
+                .get().expect("CONFIG not set")
+                .read().await;
+            let token_opt = config_guard.tokens.iter()
+                .find(|t| token_digest(t) == digest);
+            if let Some(token) = token_opt {
+                return Some(token.clone());
+            }
+        }
+    }
+
+    match query_pairs.get("token") {
+        Some(token) => find_access_token(token).await,
+        None => None,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hyper::header::HeaderValue;
+
+    use crate::config::TokenScope;
+
+    const SECRET: &[u8] = b"correct horse battery staple";
+
+    fn plain(token: &str) -> AccessToken {
+        AccessToken::Plain(token.to_owned())
+    }
+
+    fn hashed(token_hash: String) -> AccessToken {
+        AccessToken::Detailed {
+            token: None,
+            token_hash: Some(token_hash),
+            player: None,
+            scopes: TokenScope::all(),
+        }
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let value = sign_session(SECRET, &plain("token"), 2000);
+        assert_eq!(verify_session(SECRET, &value, 1000), Some(token_digest(&plain("token"))));
+        assert!(!value.contains("token"));
+    }
+
+    #[test]
+    fn test_expired_session() {
+        let value = sign_session(SECRET, &plain("token"), 2000);
+        assert_eq!(verify_session(SECRET, &value, 2000), None);
+    }
+
+    #[test]
+    fn test_tampered_session() {
+        let value = sign_session(SECRET, &plain("token"), 2000);
+        let extended = value.replacen(".2000.", ".9999.", 1);
+        assert_ne!(value, extended);
+        assert_eq!(verify_session(SECRET, &extended, 1000), None);
+        assert_eq!(verify_session(b"another secret", &value, 1000), None);
+        assert_eq!(verify_session(SECRET, "garbage", 1000), None);
+    }
+
+    #[test]
+    fn test_header_parsing() {
+        let mut headers = HeaderMap::new();
+        headers.insert("Authorization", HeaderValue::from_static("bearer abc123"));
+        headers.append("Cookie", HeaderValue::from_static("theme=dark; wordle_archive_session=xyz"));
+        assert_eq!(get_bearer_token(&headers), Some("abc123"));
+        assert_eq!(get_cookie(&headers, SESSION_COOKIE_NAME), Some("xyz"));
+        assert_eq!(get_cookie(&headers, "missing"), None);
+
+        headers.insert("Authorization", HeaderValue::from_static("Basic dXNlcjpwYXNz"));
+        assert_eq!(get_bearer_token(&headers), None);
+    }
+
+    #[test]
+    fn test_token_hash() {
+        let token_hash = hash_token("s3cr3t");
+        assert!(is_valid_token_hash(&token_hash));
+        assert!(!token_hash.contains("s3cr3t"));
+        assert_ne!(token_hash, hash_token("s3cr3t"));
+
+        let access_token = hashed(token_hash);
+        assert!(token_matches(&access_token, "s3cr3t"));
+        assert!(!token_matches(&access_token, "s3cr3"));
+        assert!(!token_matches(&access_token, ""));
+
+        assert!(token_matches(&plain("s3cr3t"), "s3cr3t"));
+        assert!(!token_matches(&plain("s3cr3t"), "s3cr3t!"));
+    }
+
+    #[test]
+    fn test_invalid_token_hash() {
+        let salt = [7u8; TOKEN_HASH_SALT_LENGTH];
+        let token_hash = hash_token_with_salt("s3cr3t", &salt);
+        assert!(is_valid_token_hash(&token_hash));
+        assert!(!is_valid_token_hash(&token_hash.replacen(TOKEN_HASH_ALGORITHM, "md5", 1)));
+        assert!(!is_valid_token_hash(&format!("{}$extra", token_hash)));
+        assert!(!is_valid_token_hash("s3cr3t"));
+        assert!(!token_matches(&hashed("s3cr3t".to_owned()), "s3cr3t"));
+    }
+}
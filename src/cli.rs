@@ -0,0 +1,707 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::pin::pin;
+use std::process::ExitCode;
+
+use chrono::NaiveDate;
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use clap::{Args, Subcommand, ValueEnum};
+use futures_util::StreamExt;
+use rand::{RngCore, thread_rng};
+use tracing::error;
+
+use crate::{parse_populate_date, to_path_segments};
+use crate::auth::hash_token as hash_access_token;
+use crate::config::{CONFIG, TokenScope};
+use crate::database::{DbConnection, initialize_pool, OptionResult, StoreResult};
+use crate::export::{ExportError, ExportFormat, RecordEncoder, write_export};
+use crate::import::{find_player, find_site, ImportFormat, read_records, record_to_puzzle};
+use crate::model::Player;
+use crate::nyt::{find_nyt_site, import_games, NytDump, NytImportOutcome};
+use crate::parsers::parse_puzzle_data;
+use crate::verify::reparse_puzzle;
+
+
+#[derive(Subcommand)]
+pub(crate) enum Command {
+    /// Runs the web server. This is the default if no subcommand is given.
+    Serve,
+
+    /// Applies any pending database migrations, then exits.
+    Migrate(MigrateArgs),
+
+    /// Checks the configuration file and the database connection, then exits.
+    CheckConfig,
+
+    /// Parses a shared puzzle result and stores it in the database.
+    Populate(PopulateArgs),
+
+    /// Exports all sites and puzzles.
+    Export(ExportArgs),
+
+    /// Imports puzzles in the format written by `export`.
+    Import(ImportArgs),
+
+    /// Imports games from the `nyt-wordle-moogle/<uid>` localStorage entry of NYT Wordle.
+    ImportNyt(ImportNytArgs),
+
+    /// Parses all stored puzzles again and reports those whose pattern or attempts differ.
+    Verify(VerifyArgs),
+
+    /// Hashes an access token and outputs the corresponding entry for `tokens` in the
+    /// configuration file.
+    HashToken(HashTokenArgs),
+}
+
+#[derive(Args)]
+pub(crate) struct MigrateArgs {
+    /// Lists which migrations have been applied and which are pending instead of applying them.
+    #[clap(long)] pub status: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct PopulateArgs {
+    /// The name of the puzzle site.
+    #[clap(long)] pub site: String,
+
+    /// The file containing the result as shared by the site, or `-` for standard input.
+    #[clap(long)] pub result: PathBuf,
+
+    /// The file containing the guesses, one per line, or `-` for standard input.
+    #[clap(long)] pub guesses: PathBuf,
+
+    /// The date on which the puzzle was solved (YYYY-MM-DD); defaults to today.
+    #[clap(long)] pub date: Option<NaiveDate>,
+
+    /// The daily ordinal of the puzzle, or `auto` to use the next free one.
+    #[clap(long, default_value = "auto")] pub day_ordinal: String,
+
+    /// Replaces any puzzle already stored for the same site, player, date and daily ordinal.
+    #[clap(long)] pub overwrite: bool,
+
+    /// The name of the player to whom the puzzle belongs; defaults to `default_player` from the
+    /// configuration.
+    #[clap(long)] pub player: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct ExportArgs {
+    /// The format in which to export the data.
+    #[clap(long, value_enum, default_value = "jsonl")] pub format: ExportFormat,
+
+    /// The file to write the export to, or `-` for standard output.
+    #[clap(long, default_value = "-")] pub output: PathBuf,
+}
+
+#[derive(Args)]
+pub(crate) struct ImportArgs {
+    /// The format of the data to import.
+    #[clap(long, value_enum, default_value = "jsonl")] pub format: ImportFormat,
+
+    /// The file to import, or `-` for standard input.
+    #[clap(long, default_value = "-")] pub input: PathBuf,
+
+    /// Takes over the pattern and attempts as given instead of parsing the result again.
+    #[clap(long)] pub trust_parsed: bool,
+
+    /// The name of the player to whom records without a player belong; defaults to
+    /// `default_player` from the configuration.
+    #[clap(long)] pub player: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct ImportNytArgs {
+    /// The file containing the localStorage entry (or a JSON array of entries), or `-` for
+    /// standard input.
+    #[clap(long, default_value = "-")] pub input: PathBuf,
+
+    /// The name of the puzzle site; defaults to the site with the NYT Wordle URL.
+    #[clap(long)] pub site: Option<String>,
+
+    /// The name of the player to whom the games belong; defaults to `default_player` from the
+    /// configuration.
+    #[clap(long)] pub player: Option<String>,
+}
+
+#[derive(Args)]
+pub(crate) struct VerifyArgs {
+    /// Rewrites the puzzles that differ or lack the raw pattern with the newly parsed data, in a
+    /// single transaction.
+    #[clap(long)] pub reparse: bool,
+}
+
+#[derive(Args)]
+pub(crate) struct HashTokenArgs {
+    /// The file containing the token, or `-` for standard input.
+    #[clap(long, default_value = "-", conflicts_with = "generate")] pub input: PathBuf,
+
+    /// Generates a random token instead of reading one, and outputs it as well.
+    #[clap(long)] pub generate: bool,
+
+    /// The name of the player to whom the token belongs; defaults to `default_player` from the
+    /// configuration.
+    #[clap(long)] pub player: Option<String>,
+
+    /// A scope of the token; may be passed multiple times. Defaults to all scopes.
+    #[clap(long = "scope", value_enum)] pub scopes: Vec<TokenScope>,
+}
+
+
+fn read_input(path: &Path) -> Option<String> {
+    let mut buf = String::new();
+    let read_res = if path == Path::new("-") {
+        io::stdin().read_to_string(&mut buf)
+    } else {
+        match File::open(path) {
+            Ok(mut f) => f.read_to_string(&mut buf),
+            Err(e) => {
+                error!("failed to open {}: {}", path.display(), e);
+                return None;
+            },
+        }
+    };
+    if let Err(e) = read_res {
+        error!("failed to read {}: {}", path.display(), e);
+        return None;
+    }
+    Some(buf.replace('\r', ""))
+}
+
+
+/// Looks up the player with the given name, falling back to the default player from the
+/// configuration.
+async fn find_cli_player(db_conn: &DbConnection, name_opt: Option<&str>) -> Option<Player> {
+    let name = match name_opt {
+        Some(n) => n.to_owned(),
+        None => {
+            let config_guard = CONFIG
+                .get().expect("CONFIG not set")
+                .read().await;
+            config_guard.default_player.clone()
+        },
+    };
+    match db_conn.get_player_by_name(&name).await {
+        OptionResult::Present(p) => Some(p),
+        OptionResult::Absent => {
+            error!("player {:?} not found", name);
+            None
+        },
+        OptionResult::Error => None, // error already output
+    }
+}
+
+
+pub(crate) async fn migrate(args: &MigrateArgs) -> ExitCode {
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    if args.status {
+        match db_conn.get_schema_version().await {
+            Some(v) => println!("schema version: {}", v),
+            None => println!("schema version: unknown"),
+        }
+        let status = match db_conn.get_migration_status().await {
+            Some(s) => s,
+            None => return ExitCode::FAILURE, // error already output
+        };
+        for (migration, pending) in status {
+            println!("{} {:?}", if pending { "pending" } else { "applied" }, migration);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    if !db_conn.run_migrations().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    println!("database schema is up to date");
+    ExitCode::SUCCESS
+}
+
+
+pub(crate) async fn check_config() -> ExitCode {
+    // the config has already been parsed successfully at this point
+    {
+        let config_guard = CONFIG
+            .get().expect("CONFIG not set")
+            .read().await;
+        let mut valid = true;
+        if to_path_segments(&config_guard.base_path, true).is_none() {
+            error!("base_path {:?} cannot be split into path segments", config_guard.base_path);
+            valid = false;
+        }
+        if !valid {
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let status = match db_conn.get_migration_status().await {
+        Some(s) => s,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let pending_count = status.iter()
+        .filter(|(_migration, pending)| *pending)
+        .count();
+
+    println!("configuration is valid and the database is reachable");
+    if pending_count > 0 {
+        println!("{} migration(s) pending; they will be applied by `serve` or `migrate`", pending_count);
+    }
+    ExitCode::SUCCESS
+}
+
+
+pub(crate) async fn populate(args: &PopulateArgs) -> ExitCode {
+    if args.result == Path::new("-") && args.guesses == Path::new("-") {
+        error!("only one of --result and --guesses may be read from standard input");
+        return ExitCode::FAILURE;
+    }
+
+    let date_string = args.date.map(|d| d.format("%Y-%m-%d").to_string());
+    let date = match parse_populate_date(date_string.as_deref()) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("{}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+    let day_ordinal_opt = if args.day_ordinal == "auto" || args.day_ordinal.is_empty() {
+        None
+    } else {
+        match args.day_ordinal.parse() {
+            Ok(d) => Some(d),
+            Err(_) => {
+                error!("--day-ordinal must be a number or \"auto\"");
+                return ExitCode::FAILURE;
+            },
+        }
+    };
+
+    let result = match read_input(&args.result) {
+        Some(r) => r,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let raw_solution = match read_input(&args.guesses) {
+        Some(g) => g,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let site = match sites.iter().find(|s| s.name == args.site) {
+        Some(s) => s,
+        None => {
+            error!("site {:?} not found", args.site);
+            return ExitCode::FAILURE;
+        },
+    };
+    let player = match find_cli_player(&db_conn, args.player.as_deref()).await {
+        Some(p) => p,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    let puzzle_data = match parse_puzzle_data(site, &result, raw_solution.trim_end_matches('\n')) {
+        Ok(pd) => pd,
+        Err(e) => {
+            error!("failed to parse puzzle: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    let day_ordinal = match day_ordinal_opt {
+        Some(dord) => dord,
+        None => match db_conn.get_next_free_day_ordinal(site.id, player.id, date, None).await {
+            Some(dord) => dord,
+            None => return ExitCode::FAILURE, // error already output
+        },
+    };
+    let puzzle = puzzle_data.into_puzzle(site.id, player.id, date, day_ordinal);
+
+    let puzzle_id = if args.overwrite {
+        match db_conn.store_or_overwrite_puzzle(&puzzle).await {
+            Some(pid) => pid,
+            None => return ExitCode::FAILURE, // error already output
+        }
+    } else {
+        match db_conn.store_puzzle(&puzzle).await {
+            StoreResult::Stored(pid) => pid,
+            StoreResult::Duplicate => {
+                let existing = match db_conn.get_puzzle_id_for_day(site.id, player.id, date, day_ordinal).await {
+                    OptionResult::Present(pid) => format!(" (puzzle {})", pid),
+                    OptionResult::Absent|OptionResult::Error => String::new(),
+                };
+                error!(
+                    "a {} puzzle has already been stored for {} on {} with daily ordinal {}{}; pass --overwrite to replace it or --day-ordinal auto to add another one",
+                    site.name, player.name, date, day_ordinal, existing,
+                );
+                return ExitCode::FAILURE;
+            },
+            StoreResult::Error => return ExitCode::FAILURE, // error already output
+        }
+    };
+
+    println!("stored puzzle {}", puzzle_id);
+    ExitCode::SUCCESS
+}
+
+
+pub(crate) async fn export(args: &ExportArgs) -> ExitCode {
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let players = match db_conn.get_players().await {
+        Some(p) => p,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    let mut writer: Box<dyn Write> = if args.output == Path::new("-") {
+        Box::new(BufWriter::new(io::stdout().lock()))
+    } else {
+        match File::create(&args.output) {
+            Ok(f) => Box::new(BufWriter::new(f)),
+            Err(e) => {
+                error!("failed to create {}: {}", args.output.display(), e);
+                return ExitCode::FAILURE;
+            },
+        }
+    };
+
+    let mut encoder = match RecordEncoder::new(args.format, &players) {
+        Some(e) => e,
+        None => {
+            // SQLite databases are assembled in memory
+            let puzzles = match db_conn.get_all_puzzles().await {
+                Some(p) => p,
+                None => return ExitCode::FAILURE, // error already output
+            };
+            if let Err(e) = write_export(args.format, &sites, &players, &puzzles, writer) {
+                error!("failed to export: {}", e);
+                return ExitCode::FAILURE;
+            }
+            return ExitCode::SUCCESS;
+        },
+    };
+
+    // write the other formats as the puzzles are read
+    let puzzle_stream = match db_conn.stream_all_puzzles().await {
+        Some(ps) => ps,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let mut puzzle_stream = pin!(puzzle_stream);
+    while let Some(puzzle_opt) = puzzle_stream.next().await {
+        let puzzle = match puzzle_opt {
+            Some(p) => p,
+            None => return ExitCode::FAILURE, // error already output
+        };
+        let write_res = encoder.encode(&puzzle)
+            .and_then(|data| writer.write_all(&data).map_err(ExportError::from));
+        if let Err(e) = write_res {
+            error!("failed to export: {}", e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if let Err(e) = writer.flush() {
+        error!("failed to export: {}", e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+
+pub(crate) async fn import(args: &ImportArgs) -> ExitCode {
+    let records = if args.input == Path::new("-") {
+        read_records(args.format, io::stdin().lock())
+    } else {
+        match File::open(&args.input) {
+            Ok(f) => read_records(args.format, BufReader::new(f)),
+            Err(e) => {
+                error!("failed to open {}: {}", args.input.display(), e);
+                return ExitCode::FAILURE;
+            },
+        }
+    };
+
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let players = match db_conn.get_players().await {
+        Some(p) => p,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let default_player = match find_cli_player(&db_conn, args.player.as_deref()).await {
+        Some(p) => p,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    let mut imported_count: usize = 0;
+    let mut skipped_count: usize = 0;
+    let mut failed_count: usize = 0;
+    for (line_number, record_res) in records {
+        let puzzle_res = record_res
+            .and_then(|record| {
+                let site = find_site(&record, &sites)?;
+                let player = find_player(&record, &players, &default_player)?;
+                record_to_puzzle(&record, site, player, args.trust_parsed)
+            });
+        let puzzle = match puzzle_res {
+            Ok(p) => p,
+            Err(e) => {
+                error!("line {}: {}", line_number, e);
+                failed_count += 1;
+                continue;
+            },
+        };
+
+        match db_conn.store_puzzle(&puzzle).await {
+            StoreResult::Stored(_) => imported_count += 1,
+            StoreResult::Duplicate => skipped_count += 1,
+            StoreResult::Error => {
+                // details have already been logged
+                error!("line {}: failed to store puzzle", line_number);
+                failed_count += 1;
+            },
+        }
+    }
+
+    println!(
+        "{} imported, {} skipped (already stored), {} failed",
+        imported_count, skipped_count, failed_count,
+    );
+    if failed_count > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+
+pub(crate) async fn import_nyt(args: &ImportNytArgs) -> ExitCode {
+    let input = match read_input(&args.input) {
+        Some(i) => i,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let dump: NytDump = match serde_json::from_str(&input) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("failed to decode NYT Wordle state: {}", e);
+            return ExitCode::FAILURE;
+        },
+    };
+
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let site = match find_nyt_site(&sites, args.site.as_deref()) {
+        Some(s) => s,
+        None => {
+            match &args.site {
+                Some(name) => error!("site {:?} not found", name),
+                None => error!("no NYT Wordle site found; pass --site"),
+            }
+            return ExitCode::FAILURE;
+        },
+    };
+    let player = match find_cli_player(&db_conn, args.player.as_deref()).await {
+        Some(p) => p,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    let outcomes = import_games(&db_conn, site, &player, &dump.into_games()).await;
+    let mut imported_count: usize = 0;
+    let mut skipped_count: usize = 0;
+    let mut failed_count: usize = 0;
+    for outcome in &outcomes {
+        match outcome {
+            NytImportOutcome::Imported { .. } => imported_count += 1,
+            NytImportOutcome::Skipped { .. } => skipped_count += 1,
+            NytImportOutcome::Failed { day_offset, message } => {
+                error!("Wordle {}: {}", day_offset, message);
+                failed_count += 1;
+            },
+        }
+    }
+
+    println!(
+        "{} imported, {} skipped (already stored), {} failed",
+        imported_count, skipped_count, failed_count,
+    );
+    if failed_count > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+
+pub(crate) async fn verify(args: &VerifyArgs) -> ExitCode {
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+    let mut db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return ExitCode::FAILURE, // error already output
+    };
+    let puzzles = match db_conn.get_all_puzzles().await {
+        Some(p) => p,
+        None => return ExitCode::FAILURE, // error already output
+    };
+
+    let mut differing_count: usize = 0;
+    let mut missing_raw_count: usize = 0;
+    let mut failed_count: usize = 0;
+    let mut updates = Vec::new();
+    for db_puzzle in &puzzles {
+        let stored = &db_puzzle.puzzle;
+        let description = format!(
+            "puzzle {} ({} on {}, daily ordinal {})",
+            stored.id, db_puzzle.site.name, stored.date, stored.day_ordinal,
+        );
+        let reparsed = match reparse_puzzle(db_puzzle) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("{}: {}", description, e);
+                failed_count += 1;
+                continue;
+            },
+        };
+
+        if reparsed.pattern_differs {
+            println!(
+                "{}: stored pattern {:?} differs from parsed pattern {:?}",
+                description, stored.pattern, reparsed.puzzle.pattern,
+            );
+        }
+        if reparsed.attempts_differs {
+            println!(
+                "{}: stored attempts {:?} differ from parsed attempts {:?}",
+                description, stored.attempts, reparsed.puzzle.attempts,
+            );
+        }
+        if reparsed.pattern_differs || reparsed.attempts_differs {
+            differing_count += 1;
+        }
+        if reparsed.raw_pattern_missing {
+            missing_raw_count += 1;
+        }
+        if reparsed.needs_update() {
+            updates.push(reparsed.puzzle);
+        }
+    }
+
+    println!(
+        "{} checked, {} differ, {} without raw pattern, {} failed to parse",
+        puzzles.len(), differing_count, missing_raw_count, failed_count,
+    );
+
+    if args.reparse {
+        if !updates.is_empty() && !db_conn.update_puzzles_atomically(&updates).await {
+            // error already output
+            return ExitCode::FAILURE;
+        }
+        println!("{} rewritten", updates.len());
+        if failed_count > 0 {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        }
+    } else if differing_count > 0 || failed_count > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+
+/// Quotes the string for use in a TOML file.
+fn toml_string(s: &str) -> String {
+    toml::Value::String(s.to_owned()).to_string()
+}
+
+pub(crate) fn hash_token(args: &HashTokenArgs) -> ExitCode {
+    let token = if args.generate {
+        let mut token_bytes = [0u8; 32];
+        thread_rng().fill_bytes(&mut token_bytes);
+        let token = URL_SAFE_NO_PAD.encode(token_bytes);
+        println!("token: {}", token);
+        token
+    } else {
+        let input = match read_input(&args.input) {
+            Some(i) => i,
+            None => return ExitCode::FAILURE, // error already output
+        };
+        input.trim().to_owned()
+    };
+    if token.is_empty() {
+        error!("the token is empty");
+        return ExitCode::FAILURE;
+    }
+
+    let mut entry = format!("{{ token_hash = {}", toml_string(&hash_access_token(&token)));
+    if let Some(player) = &args.player {
+        entry.push_str(&format!(", player = {}", toml_string(player)));
+    }
+    if !args.scopes.is_empty() {
+        let scope_names: Vec<String> = args.scopes.iter()
+            .filter_map(|s| s.to_possible_value())
+            .map(|v| toml_string(v.get_name()))
+            .collect();
+        entry.push_str(&format!(", scopes = [{}]", scope_names.join(", ")));
+    }
+    entry.push_str(" }");
+    println!("{}", entry);
+    ExitCode::SUCCESS
+}
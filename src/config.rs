@@ -1,13 +1,15 @@
-use std::collections::HashSet;
 use std::fs::File;
 use std::io::Read;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
+use clap::ValueEnum;
 use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use tokio::sync::RwLock;
-use tracing::error;
+use tracing::{error, warn};
+
+use crate::auth::is_valid_token_hash;
 
 
 pub(crate) static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
@@ -19,8 +21,125 @@ pub(crate) struct Config {
     pub listen_addr: SocketAddr,
     pub base_path: String,
     pub db_conn_string: String,
-    #[serde(default)] pub write_tokens: HashSet<String>,
+    #[serde(default = "Config::default_db_pool_size")] pub db_pool_size: usize,
+    #[serde(default = "Config::default_db_pool_timeout_ms")] pub db_pool_timeout_ms: u64,
+    #[serde(default, alias = "write_tokens")] pub tokens: Vec<AccessToken>,
     #[serde(default)] pub spoiler_protection_days: i64,
+    #[serde(default = "Config::default_default_player")] pub default_player: String,
+    #[serde(default)] pub session_secret: Option<String>,
+    #[serde(default = "Config::default_session_lifetime_days")] pub session_lifetime_days: i64,
+    #[serde(default)] pub secure_session_cookie: bool,
+}
+impl Config {
+    fn default_db_pool_size() -> usize { 8 }
+    fn default_db_pool_timeout_ms() -> u64 { 5000 }
+    fn default_default_player() -> String { "default".to_owned() }
+    fn default_session_lifetime_days() -> i64 { 30 }
+}
+
+/// An action that a token may allow.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum TokenScope {
+    /// Viewing the solutions of puzzles that are still protected from spoilers.
+    SpoilerRead,
+    /// Recording and editing puzzles of the token's player.
+    Write,
+    /// Deleting puzzles of the token's player and exporting the whole archive.
+    Admin,
+}
+impl TokenScope {
+    /// The scopes of tokens that do not specify any.
+    pub fn all() -> Vec<Self> {
+        vec![Self::SpoilerRead, Self::Write, Self::Admin]
+    }
+}
+
+/// A token allowing a player to perform the actions of its scopes.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum AccessToken {
+    /// A token stored in plain text, belonging to the default player, with all scopes.
+    ///
+    /// Deprecated in favor of storing a hash of the token via `token_hash`.
+    Plain(String),
+    /// A token stored either in plain text or as a hash as output by the `hash-token` subcommand,
+    /// belonging to the given player (or the default player), with the given scopes (or all
+    /// scopes). Exactly one of `token` and `token_hash` must be set.
+    Detailed {
+        #[serde(default, skip_serializing_if = "Option::is_none")] token: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] token_hash: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")] player: Option<String>,
+        #[serde(default = "TokenScope::all")] scopes: Vec<TokenScope>,
+    },
+}
+impl AccessToken {
+    /// Returns the token if it is stored in plain text.
+    pub fn token(&self) -> Option<&str> {
+        match self {
+            Self::Plain(token) => Some(token),
+            Self::Detailed { token, .. } => token.as_deref(),
+        }
+    }
+
+    /// Returns the hash of the token if it is stored hashed.
+    pub fn token_hash(&self) -> Option<&str> {
+        match self {
+            Self::Plain(_) => None,
+            Self::Detailed { token_hash, .. } => token_hash.as_deref(),
+        }
+    }
+
+    /// Returns the name of the player to whom the token belongs.
+    pub fn player<'a>(&'a self, default_player: &'a str) -> &'a str {
+        match self {
+            Self::Plain(_) => default_player,
+            Self::Detailed { player, .. } => player.as_deref().unwrap_or(default_player),
+        }
+    }
+
+    /// Returns whether the token allows the actions of the given scope.
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        match self {
+            Self::Plain(_) => true,
+            Self::Detailed { scopes, .. } => scopes.contains(&scope),
+        }
+    }
+}
+
+/// Checks the access tokens of the configuration, outputting an error for each invalid one and
+/// a deprecation warning if any are stored in plain text. Returns whether all tokens are valid.
+fn check_tokens(tokens: &[AccessToken]) -> bool {
+    let mut valid = true;
+    let mut plain_count = 0;
+    for (index, access_token) in tokens.iter().enumerate() {
+        match (access_token.token(), access_token.token_hash()) {
+            (Some(_), None) => {
+                plain_count += 1;
+            },
+            (None, Some(hash)) => {
+                if !is_valid_token_hash(hash) {
+                    error!("cannot load config: token {} has an invalid token_hash", index);
+                    valid = false;
+                }
+            },
+            (Some(_), Some(_)) => {
+                error!("cannot load config: token {} has both token and token_hash set", index);
+                valid = false;
+            },
+            (None, None) => {
+                error!("cannot load config: token {} has neither token nor token_hash set", index);
+                valid = false;
+            },
+        }
+    }
+    if plain_count > 0 {
+        warn!(
+            "{} token(s) are stored in plain text, which is deprecated; use the hash-token subcommand to obtain a token_hash for each",
+            plain_count,
+        );
+    }
+    valid
 }
 
 pub(crate) fn load_config() -> Option<Config> {
@@ -46,7 +165,7 @@ pub(crate) fn load_config() -> Option<Config> {
         return None;
     }
 
-    let config = match toml::from_str(&buf) {
+    let config: Config = match toml::from_str(&buf) {
         Ok(c) => c,
         Err(e) => {
             error!("cannot load config: failed to parse config file {}: {}", config_path.display(), e);
@@ -54,5 +173,9 @@ pub(crate) fn load_config() -> Option<Config> {
         },
     };
 
+    if !check_tokens(&config.tokens) {
+        return None;
+    }
+
     Some(config)
 }
@@ -26,3 +26,93 @@ impl DbMigration for MigrationR0011ToR0012 {
         };
     }
 }
+
+
+#[derive(Debug)]
+pub(crate) struct MigrationR0012ToR0013;
+#[async_trait]
+impl DbMigration for MigrationR0012ToR0013 {
+    async fn is_required(&self, _db_client: &tokio_postgres::Client, schema_version: Option<i64>) -> Result<bool, tokio_postgres::Error> {
+        migration_utils::schema_older_than(schema_version, 13)
+    }
+
+    #[instrument(skip(db_client))]
+    async fn migrate(&self, db_client: &tokio_postgres::Client) -> bool {
+        let migration_code = include_str!("../../db/migrations/r0012_to_r0013.pgsql");
+        match db_client.batch_execute(migration_code).await {
+            Ok(_) => return true,
+            Err(e) => {
+                migration_utils::log_failure_error(self, &e);
+                migration_utils::log_manual_commands(migration_code);
+                return false;
+            },
+        };
+    }
+}
+
+
+#[derive(Debug)]
+pub(crate) struct MigrationR0013ToR0014;
+#[async_trait]
+impl DbMigration for MigrationR0013ToR0014 {
+    async fn is_required(&self, _db_client: &tokio_postgres::Client, schema_version: Option<i64>) -> Result<bool, tokio_postgres::Error> {
+        migration_utils::schema_older_than(schema_version, 14)
+    }
+
+    #[instrument(skip(db_client))]
+    async fn migrate(&self, db_client: &tokio_postgres::Client) -> bool {
+        let migration_code = include_str!("../../db/migrations/r0013_to_r0014.pgsql");
+        match db_client.batch_execute(migration_code).await {
+            Ok(_) => return true,
+            Err(e) => {
+                migration_utils::log_failure_error(self, &e);
+                migration_utils::log_manual_commands(migration_code);
+                return false;
+            },
+        };
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct MigrationR0014ToR0015;
+#[async_trait]
+impl DbMigration for MigrationR0014ToR0015 {
+    async fn is_required(&self, _db_client: &tokio_postgres::Client, schema_version: Option<i64>) -> Result<bool, tokio_postgres::Error> {
+        migration_utils::schema_older_than(schema_version, 15)
+    }
+
+    #[instrument(skip(db_client))]
+    async fn migrate(&self, db_client: &tokio_postgres::Client) -> bool {
+        let migration_code = include_str!("../../db/migrations/r0014_to_r0015.pgsql");
+        match db_client.batch_execute(migration_code).await {
+            Ok(_) => return true,
+            Err(e) => {
+                migration_utils::log_failure_error(self, &e);
+                migration_utils::log_manual_commands(migration_code);
+                return false;
+            },
+        };
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct MigrationR0015ToR0016;
+#[async_trait]
+impl DbMigration for MigrationR0015ToR0016 {
+    async fn is_required(&self, _db_client: &tokio_postgres::Client, schema_version: Option<i64>) -> Result<bool, tokio_postgres::Error> {
+        migration_utils::schema_older_than(schema_version, 16)
+    }
+
+    #[instrument(skip(db_client))]
+    async fn migrate(&self, db_client: &tokio_postgres::Client) -> bool {
+        let migration_code = include_str!("../../db/migrations/r0015_to_r0016.pgsql");
+        match db_client.batch_execute(migration_code).await {
+            Ok(_) => return true,
+            Err(e) => {
+                migration_utils::log_failure_error(self, &e);
+                migration_utils::log_manual_commands(migration_code);
+                return false;
+            },
+        };
+    }
+}
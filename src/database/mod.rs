@@ -4,16 +4,22 @@ mod migrations_r0011;
 pub(crate) mod migration_utils;
 
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use chrono::NaiveDate;
+use deadpool_postgres::{Manager, ManagerConfig, Object, Pool, RecyclingMethod, Runtime};
+use futures_util::{Stream, StreamExt};
+use once_cell::sync::OnceCell;
 use tokio_postgres::{self, NoTls};
+use tokio_postgres::error::SqlState;
 use tracing::error;
 
 use crate::config::CONFIG;
-use crate::model::{Puzzle, PuzzleSite, SiteAndPuzzle, Stats, StatsSubject};
+use crate::model::{Player, Puzzle, PuzzleSite, SiteAndPuzzle};
+use crate::stats::{PuzzleOutcome, StatsWindow};
 
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -24,86 +30,220 @@ pub(crate) enum OptionResult<T> {
 }
 
 
+/// The outcome of storing a new puzzle in the database.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum StoreResult<T> {
+    Stored(T),
+    /// Another puzzle already exists for the same site, date and daily ordinal.
+    Duplicate,
+    Error,
+}
+
+
+/// The outcome of updating an existing puzzle in the database.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub(crate) enum UpdateResult {
+    Updated,
+    /// Another puzzle already exists for the same site, date and daily ordinal.
+    Duplicate,
+    /// The puzzle to update does not exist.
+    NotFound,
+    Error,
+}
+
+
+/// The name of the constraint preventing multiple puzzles with the same site, date and daily
+/// ordinal.
+const PUZZLE_DAY_CONSTRAINT: &str = "uq__puzzles__site_puzzle_day";
+
+fn is_duplicate_puzzle_error(e: &tokio_postgres::Error) -> bool {
+    match e.as_db_error() {
+        Some(db_error) => {
+            db_error.code() == &SqlState::UNIQUE_VIOLATION
+                && db_error.constraint() == Some(PUZZLE_DAY_CONSTRAINT)
+        },
+        None => false,
+    }
+}
+
+
+/// All migrations, in the order in which they must be applied.
+const ALL_MIGRATIONS: [&dyn DbMigration; 15] = [
+    &migrations_r0001::MigrationR0001ToR0002,
+    &migrations_r0001::MigrationR0002ToR0003,
+    &migrations_r0001::MigrationR0003ToR0004,
+    &migrations_r0001::MigrationR0004ToR0005,
+    &migrations_r0001::MigrationR0005ToR0006,
+    &migrations_r0006::MigrationR0006ToR0007,
+    &migrations_r0006::MigrationR0007ToR0008,
+    &migrations_r0006::MigrationR0008ToR0009,
+    &migrations_r0006::MigrationR0009ToR0010,
+    &migrations_r0006::MigrationR0010ToR0011,
+    &migrations_r0011::MigrationR0011ToR0012,
+    &migrations_r0011::MigrationR0012ToR0013,
+    &migrations_r0011::MigrationR0013ToR0014,
+    &migrations_r0011::MigrationR0014ToR0015,
+    &migrations_r0011::MigrationR0015ToR0016,
+];
+
+
+pub(crate) static DB_POOL: OnceCell<Pool> = OnceCell::new();
+
+
+/// Creates the database connection pool according to the current configuration and stores it in
+/// [`DB_POOL`].
+pub(crate) async fn initialize_pool() -> bool {
+    let (conn_string, pool_size, pool_timeout_ms) = {
+        let config_guard = CONFIG
+            .get().expect("no CONFIG set")
+            .read().await;
+        (
+            config_guard.db_conn_string.clone(),
+            config_guard.db_pool_size,
+            config_guard.db_pool_timeout_ms,
+        )
+    };
+
+    let pool = match create_pool(&conn_string, pool_size, pool_timeout_ms) {
+        Some(p) => p,
+        None => return false,
+    };
+    if DB_POOL.set(pool).is_err() {
+        error!("database connection pool has already been initialized");
+        return false;
+    }
+    true
+}
+
+fn create_pool(conn_string: &str, pool_size: usize, pool_timeout_ms: u64) -> Option<Pool> {
+    if pool_size == 0 {
+        // the pool could be created but would never hand out a connection
+        error!("db_pool_size must be at least 1");
+        return None;
+    }
+
+    let pg_config: tokio_postgres::Config = match conn_string.parse() {
+        Ok(pc) => pc,
+        Err(e) => {
+            error!("failed to parse database connection string: {}", e);
+            return None;
+        },
+    };
+    let manager_config = ManagerConfig {
+        recycling_method: RecyclingMethod::Fast,
+    };
+    let manager = Manager::from_config(pg_config, NoTls, manager_config);
+    let pool_timeout = Duration::from_millis(pool_timeout_ms);
+    let pool_res = Pool::builder(manager)
+        .max_size(pool_size)
+        .wait_timeout(Some(pool_timeout))
+        .create_timeout(Some(pool_timeout))
+        .runtime(Runtime::Tokio1)
+        .build();
+    match pool_res {
+        Ok(p) => Some(p),
+        Err(e) => {
+            error!("failed to create database connection pool: {}", e);
+            None
+        },
+    }
+}
+
+/// Creates the database connection pool for tests from the connection string in the environment
+/// variable `WORDLE_ARCHIVE_TEST_DB`. Returns `false` if the variable is not set.
+#[cfg(test)]
+pub(crate) fn initialize_test_pool() -> bool {
+    let conn_string = match std::env::var("WORDLE_ARCHIVE_TEST_DB") {
+        Ok(cs) => cs,
+        Err(_) => return false,
+    };
+    if DB_POOL.get().is_none() {
+        let pool = create_pool(&conn_string, 2, 5000)
+            .expect("failed to create test database connection pool");
+        let _ = DB_POOL.set(pool);
+    }
+    true
+}
+
+
 pub(crate) struct DbConnection {
-    client: tokio_postgres::Client,
+    client: Object,
 }
 impl DbConnection {
-    pub async fn new() -> Option<Self> {
-        let conn_string = {
-            let config_guard = CONFIG
-                .get().expect("no CONFIG set")
-                .read().await;
-            config_guard.db_conn_string.clone()
-        };
-
-        let (client, connection) = match tokio_postgres::connect(&conn_string, NoTls).await {
-            Ok(cc) => cc,
+    /// Borrows a connection from the connection pool.
+    ///
+    /// The connection is returned to the pool once this value is dropped.
+    pub async fn get() -> Option<Self> {
+        let pool = DB_POOL
+            .get().expect("no DB_POOL set");
+        match pool.get().await {
+            Ok(client) => Some(Self { client }),
             Err(e) => {
-                error!("error connecting to database: {}", e);
-                return None;
+                error!("error obtaining database connection from pool: {}", e);
+                None
             },
-        };
+        }
+    }
 
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                error!("database connection error: {}", e);
-            }
-        });
+    /// Returns the current schema version, or `None` if the database predates schema versioning
+    /// (or has not been set up at all).
+    pub async fn get_schema_version(&self) -> Option<i64> {
+        let row_res = self.client.query_one(
+            "SELECT schema_version FROM wordle_archive.schema_version",
+            &[],
+        ).await;
+        // fail silently
+        row_res.ok().map(|row| row.get(0))
+    }
 
+    /// Applies all pending schema migrations. Should be run once at startup.
+    pub async fn run_migrations(&self) -> bool {
         // pre-fetch current schema version
-        let mut current_schema_version = None;
-        {
-            let row_res = client.query_one(
-                "SELECT schema_version FROM wordle_archive.schema_version",
-                &[],
-            ).await;
-            if let Ok(row) = row_res {
-                let ver: i64 = row.get(0);
-                current_schema_version = Some(ver);
-            }
-            // fail silently
-        }
+        let current_schema_version = self.get_schema_version().await;
 
         // run migrations
-        let current_migrations: [&(dyn DbMigration); 11] = [
-            &migrations_r0001::MigrationR0001ToR0002,
-            &migrations_r0001::MigrationR0002ToR0003,
-            &migrations_r0001::MigrationR0003ToR0004,
-            &migrations_r0001::MigrationR0004ToR0005,
-            &migrations_r0001::MigrationR0005ToR0006,
-            &migrations_r0006::MigrationR0006ToR0007,
-            &migrations_r0006::MigrationR0007ToR0008,
-            &migrations_r0006::MigrationR0008ToR0009,
-            &migrations_r0006::MigrationR0009ToR0010,
-            &migrations_r0006::MigrationR0010ToR0011,
-            &migrations_r0011::MigrationR0011ToR0012,
-        ];
-        for migration in current_migrations {
-            match migration.is_required(&client, current_schema_version).await {
+        for migration in ALL_MIGRATIONS {
+            match migration.is_required(&self.client, current_schema_version).await {
                 Ok(false) => continue,
                 Ok(true) => {
-                    if !migration.migrate(&client).await {
+                    if !migration.migrate(&self.client).await {
                         // failure information has already been logged
-                        return None;
+                        return false;
                     }
                 },
                 Err(e) => {
                     error!("failed to ascertain whether migration {:?} is necessary: {}", migration, e);
-                    return None;
+                    return false;
                 },
             };
         }
 
-        Some(Self {
-            client
-        })
+        true
+    }
+
+    /// Returns each known migration along with whether it still has to be applied.
+    pub async fn get_migration_status(&self) -> Option<Vec<(&'static dyn DbMigration, bool)>> {
+        let current_schema_version = self.get_schema_version().await;
+
+        let mut status = Vec::with_capacity(ALL_MIGRATIONS.len());
+        for migration in ALL_MIGRATIONS {
+            match migration.is_required(&self.client, current_schema_version).await {
+                Ok(required) => status.push((migration, required)),
+                Err(e) => {
+                    error!("failed to ascertain whether migration {:?} is necessary: {}", migration, e);
+                    return None;
+                },
+            }
+        }
+        Some(status)
     }
 
     pub async fn get_sites(&self) -> Option<Vec<PuzzleSite>> {
         let rows_res = self.client.query(
             "
                 SELECT
-                    id, name, url, css_class, variant, notes, available
+                    id, name, url, css_class, variant, notes, available, max_attempts,
+                    streak_max_gap_days
                 FROM
                     wordle_archive.sites
                 ORDER BY
@@ -126,43 +266,98 @@ impl DbConnection {
         Some(sites)
     }
 
-    pub async fn get_solved_sites_for_date(&self, date: NaiveDate) -> Option<HashSet<i64>> {
+    pub async fn get_players(&self) -> Option<Vec<Player>> {
+        let rows_res = self.client.query(
+            "SELECT id, name FROM wordle_archive.players ORDER BY id",
+            &[],
+        ).await;
+        match rows_res {
+            Ok(rows) => Some(rows.iter().map(|r| Player { id: r.get(0), name: r.get(1) }).collect()),
+            Err(e) => {
+                error!("error querying players: {}", e);
+                None
+            },
+        }
+    }
+
+    pub async fn get_player_by_name(&self, name: &str) -> OptionResult<Player> {
+        let row_opt_res = self.client.query_opt(
+            "SELECT id, name FROM wordle_archive.players WHERE name = $1",
+            &[&name],
+        ).await;
+        match row_opt_res {
+            Ok(Some(r)) => OptionResult::Present(Player { id: r.get(0), name: r.get(1) }),
+            Ok(None) => OptionResult::Absent,
+            Err(e) => {
+                error!("failed to obtain player by name: {}", e);
+                OptionResult::Error
+            },
+        }
+    }
+
+    /// Returns the number of puzzles stored by the given player on the given date for each site.
+    /// Sites without any puzzles on that date are omitted.
+    pub async fn get_puzzle_counts_for_date(&self, player_id: i64, date: NaiveDate) -> Option<HashMap<i64, i64>> {
         let rows_res = self.client.query(
             "
-                SELECT s.id
-                FROM wordle_archive.sites s
-                WHERE EXISTS (
-                    SELECT 1 FROM wordle_archive.puzzles p
-                    WHERE p.site_id = s.id
-                    AND p.puzzle_date = $1
-                )
+                SELECT p.site_id, COUNT(*)
+                FROM wordle_archive.puzzles p
+                WHERE p.player_id = $1
+                AND p.puzzle_date = $2
+                GROUP BY p.site_id
             ",
-            &[&date],
+            &[&player_id, &date],
         ).await;
-        let mut site_ids = HashSet::new();
+        let mut site_to_count = HashMap::new();
         let rows = match rows_res {
             Ok(rs) => rs,
             Err(e) => {
-                error!("error querying solved sites: {}", e);
+                error!("error querying puzzle counts: {}", e);
                 return None;
             },
         };
         for row in rows {
             let site_id: i64 = row.get(0);
-            site_ids.insert(site_id);
+            let count: i64 = row.get(1);
+            site_to_count.insert(site_id, count);
         }
-        Some(site_ids)
+        Some(site_to_count)
     }
 
-    pub async fn get_most_recent_puzzle_date(&self) -> OptionResult<NaiveDate> {
+    /// Returns the months (as their first day) in which the given player has stored puzzles, most
+    /// recent first.
+    pub async fn get_puzzle_months(&self, player_id: i64) -> Option<Vec<NaiveDate>> {
+        let rows_res = self.client.query(
+            "
+                SELECT DISTINCT CAST(date_trunc('month', puzzle_date) AS date) puzzle_month
+                FROM wordle_archive.puzzles
+                WHERE player_id = $1
+                ORDER BY puzzle_month DESC
+            ",
+            &[&player_id],
+        ).await;
+        match rows_res {
+            Ok(rows) => Some(rows.iter().map(|r| r.get(0)).collect()),
+            Err(e) => {
+                error!("error querying puzzle months: {}", e);
+                None
+            },
+        }
+    }
+
+    pub async fn get_most_recent_puzzle_date(&self, player_id: i64) -> OptionResult<NaiveDate> {
         let row_opt_res = self.client.query_opt(
-            "SELECT MAX(puzzle_date) FROM wordle_archive.puzzles",
-            &[],
+            "SELECT MAX(puzzle_date) FROM wordle_archive.puzzles WHERE player_id = $1",
+            &[&player_id],
         ).await;
         match row_opt_res {
             Ok(Some(r)) => {
-                let date: NaiveDate = r.get(0);
-                OptionResult::Present(date)
+                // MAX returns NULL if there are no puzzles
+                let date_opt: Option<NaiveDate> = r.get(0);
+                match date_opt {
+                    Some(date) => OptionResult::Present(date),
+                    None => OptionResult::Absent,
+                }
             },
             Ok(None) => OptionResult::Absent,
             Err(e) => {
@@ -180,6 +375,8 @@ impl DbConnection {
         let variant = row.get(4);
         let notes = row.get(5);
         let available = row.get(6);
+        let max_attempts = row.get(7);
+        let streak_max_gap_days = row.get(8);
 
         PuzzleSite {
             id: site_id,
@@ -189,13 +386,15 @@ impl DbConnection {
             variant,
             notes,
             available,
+            max_attempts,
+            streak_max_gap_days,
         }
     }
 
     fn row_to_site_and_puzzle(row: &tokio_postgres::Row) -> SiteAndPuzzle {
         let site = Self::row_to_site(row);
-        const PUZZLE_OFFSET: usize = 7;
-        let id = row.get(PUZZLE_OFFSET + 0);
+        const PUZZLE_OFFSET: usize = 9;
+        let id = row.get(PUZZLE_OFFSET);
         let date = row.get(PUZZLE_OFFSET + 1);
         let day_ordinal = row.get(PUZZLE_OFFSET + 2);
         let head = row.get(PUZZLE_OFFSET + 3);
@@ -204,10 +403,12 @@ impl DbConnection {
         let solution = row.get(PUZZLE_OFFSET + 6);
         let attempts = row.get(PUZZLE_OFFSET + 7);
         let raw_pattern = row.get(PUZZLE_OFFSET + 8);
+        let player_id = row.get(PUZZLE_OFFSET + 9);
 
         let puzzle = Puzzle {
             id,
             site_id: site.id,
+            player_id,
             date,
             day_ordinal,
             head,
@@ -223,20 +424,58 @@ impl DbConnection {
         }
     }
 
-    pub async fn get_puzzles_on_date(&self, date: NaiveDate) -> Option<Vec<SiteAndPuzzle>> {
+    pub async fn get_puzzles_on_date(&self, player_id: i64, date: NaiveDate) -> Option<Vec<SiteAndPuzzle>> {
         let rows_res = self.client.query(
             "
                 SELECT
                     site_id, site_name, site_url, site_css_class, variant, notes, available,
+                    max_attempts, streak_max_gap_days,
                     puzzle_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts,
-                    raw_pattern
+                    raw_pattern, player_id
                 FROM
                     wordle_archive.sites_and_puzzles
                 WHERE
-                    puzzle_date = $1
+                    player_id = $1
+                    AND puzzle_date = $2
                 ORDER BY
                     ordering, site_id, day_ordinal
             ",
+            &[&player_id, &date],
+        ).await;
+        let rows = match rows_res {
+            Ok(r) => r,
+            Err(e) => {
+                error!("failed to obtain puzzles: {}", e);
+                return None;
+            },
+        };
+
+        let mut puzzles = Vec::new();
+        for row in rows {
+            let site_and_puzzle = Self::row_to_site_and_puzzle(&row);
+            puzzles.push(site_and_puzzle);
+        }
+
+        Some(puzzles)
+    }
+
+    /// Returns the puzzles of all players on the given date, ordered by site, daily ordinal and
+    /// player.
+    pub async fn get_puzzles_of_all_players_on_date(&self, date: NaiveDate) -> Option<Vec<SiteAndPuzzle>> {
+        let rows_res = self.client.query(
+            "
+                SELECT
+                    site_id, site_name, site_url, site_css_class, variant, notes, available,
+                    max_attempts, streak_max_gap_days,
+                    puzzle_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts,
+                    raw_pattern, player_id
+                FROM
+                    wordle_archive.sites_and_puzzles
+                WHERE
+                    puzzle_date = $1
+                ORDER BY
+                    ordering, site_id, day_ordinal, player_id
+            ",
             &[&date],
         ).await;
         let rows = match rows_res {
@@ -256,13 +495,94 @@ impl DbConnection {
         Some(puzzles)
     }
 
+    /// Returns all stored puzzles, oldest first.
+    pub async fn get_all_puzzles(&self) -> Option<Vec<SiteAndPuzzle>> {
+        let rows_res = self.client.query(
+            "
+                SELECT
+                    site_id, site_name, site_url, site_css_class, variant, notes, available,
+                    max_attempts, streak_max_gap_days,
+                    puzzle_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts,
+                    raw_pattern, player_id
+                FROM
+                    wordle_archive.sites_and_puzzles
+                ORDER BY
+                    puzzle_date, player_id, ordering, site_id, day_ordinal
+            ",
+            &[],
+        ).await;
+        let rows = match rows_res {
+            Ok(r) => r,
+            Err(e) => {
+                error!("failed to obtain all puzzles: {}", e);
+                return None;
+            },
+        };
+
+        let mut puzzles = Vec::with_capacity(rows.len());
+        for row in rows {
+            let site_and_puzzle = Self::row_to_site_and_puzzle(&row);
+            puzzles.push(site_and_puzzle);
+        }
+
+        Some(puzzles)
+    }
+
+    /// Returns all stored puzzles, oldest first, as they are read from the database. A puzzle that
+    /// cannot be read is returned as `None` (and the error logged), after which the stream ends.
+    ///
+    /// The stream does not borrow the connection, but the connection must be kept until the stream
+    /// has ended.
+    pub async fn stream_all_puzzles(&self) -> Option<impl Stream<Item = Option<SiteAndPuzzle>>> {
+        let row_stream_res = self.client.query_raw(
+            "
+                SELECT
+                    site_id, site_name, site_url, site_css_class, variant, notes, available,
+                    max_attempts, streak_max_gap_days,
+                    puzzle_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts,
+                    raw_pattern, player_id
+                FROM
+                    wordle_archive.sites_and_puzzles
+                ORDER BY
+                    puzzle_date, player_id, ordering, site_id, day_ordinal
+            ",
+            std::iter::empty::<i64>(),
+        ).await;
+        let row_stream = match row_stream_res {
+            Ok(rs) => rs,
+            Err(e) => {
+                error!("failed to obtain all puzzles: {}", e);
+                return None;
+            },
+        };
+
+        let puzzle_stream = row_stream
+            .map(|row_res| match row_res {
+                Ok(row) => Some(Self::row_to_site_and_puzzle(&row)),
+                Err(e) => {
+                    error!("failed to read puzzle: {}", e);
+                    None
+                },
+            })
+            .scan(false, |failed, puzzle_opt| {
+                // end the stream after the first failure
+                if *failed {
+                    return std::future::ready(None);
+                }
+                *failed = puzzle_opt.is_none();
+                std::future::ready(Some(puzzle_opt))
+            });
+        Some(puzzle_stream)
+    }
+
     pub async fn get_puzzle_by_id(&self, id: i64) -> OptionResult<SiteAndPuzzle> {
         let row_opt_res = self.client.query_opt(
             "
                 SELECT
                     site_id, site_name, site_url, site_css_class, variant, notes, available,
+                    max_attempts, streak_max_gap_days,
                     puzzle_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts,
-                    raw_pattern
+                    raw_pattern, player_id
                 FROM
                     wordle_archive.sites_and_puzzles
                 WHERE
@@ -283,156 +603,271 @@ impl DbConnection {
         }
     }
 
-    pub async fn get_stats(&self) -> Option<Vec<Stats>> {
-        let mut all_stats = Vec::new();
+    /// Returns the current statistics revision, which changes whenever a puzzle or site is
+    /// modified.
+    pub async fn get_stats_revision(&self) -> Option<i64> {
+        let row_res = self.client.query_one(
+            "SELECT revision FROM wordle_archive.stats_revision",
+            &[],
+        ).await;
+        match row_res {
+            Ok(row) => Some(row.get(0)),
+            Err(e) => {
+                error!("failed to obtain statistics revision: {}", e);
+                None
+            },
+        }
+    }
 
-        // global stats
-        {
-            let global_row_res = self.client.query_one(
-                "
-                    SELECT
-                        puzzles_won, puzzles_lost, average_attempts, longest_streak
-                    FROM
-                        wordle_archive.global_stats
-                ",
-                &[],
-            ).await;
-            let global_row = match global_row_res {
-                Ok(r) => r,
-                Err(e) => {
-                    error!("failed to obtain global statistics: {}", e);
-                    return None;
-                },
-            };
+    /// Returns the outcomes of the puzzles within the given window, ordered by player, site, date
+    /// and daily ordinal. If `player_id` is given, only the puzzles of that player are returned;
+    /// otherwise, those of all players.
+    pub async fn get_puzzle_outcomes(&self, window: &StatsWindow, player_id: Option<i64>) -> Option<Vec<PuzzleOutcome>> {
+        let rows_res = self.client.query(
+            "
+                SELECT
+                    player_id, site_id, puzzle_date, day_ordinal, attempts
+                FROM
+                    wordle_archive.puzzles
+                WHERE
+                    ($1::date IS NULL OR puzzle_date >= $1)
+                    AND ($2::date IS NULL OR puzzle_date <= $2)
+                    AND ($3::bigint IS NULL OR player_id = $3)
+                ORDER BY
+                    player_id, site_id, puzzle_date, day_ordinal
+            ",
+            &[&window.from, &window.to, &player_id],
+        ).await;
+        let rows = match rows_res {
+            Ok(rs) => rs,
+            Err(e) => {
+                error!("failed to obtain puzzle outcomes: {}", e);
+                return None;
+            },
+        };
 
-            {
-                let puzzles_won = global_row.get(0);
-                let puzzles_lost = global_row.get(1);
-                let average_attempts = global_row.get(2);
-                let longest_streak = global_row.get(3);
-                let current_streak = None;
-
-                let stats = Stats {
-                    subject: StatsSubject::Global,
-                    puzzles_won,
-                    puzzles_lost,
-                    average_attempts,
-                    longest_streak,
-                    current_streak,
-                };
-                all_stats.push(stats);
-            }
+        let mut outcomes = Vec::with_capacity(rows.len());
+        for row in rows {
+            let player_id = row.get(0);
+            let site_id = row.get(1);
+            let date = row.get(2);
+            let day_ordinal = row.get(3);
+            let attempts = row.get(4);
+            outcomes.push(PuzzleOutcome { player_id, site_id, date, day_ordinal, attempts });
         }
+        Some(outcomes)
+    }
 
-        // variant stats
-        {
-            let rows_res = self.client.query(
-                "
-                    SELECT
-                        variant,
-                        puzzles_won, puzzles_lost, average_attempts, longest_streak
-                    FROM
-                        wordle_archive.variant_stats
-                    ORDER BY
-                        variant
-                ",
-                &[],
-            ).await;
-            let rows = match rows_res {
-                Ok(rs) => rs,
-                Err(e) => {
-                    error!("failed to obtain statistics: {}", e);
-                    return None;
-                },
-            };
-
-            for row in rows {
-                let variant = row.get(0);
-                let puzzles_won = row.get(1);
-                let puzzles_lost = row.get(2);
-                let average_attempts = row.get(3);
-                let longest_streak = row.get(4);
-                let current_streak = None;
-
-                let stats = Stats {
-                    subject: StatsSubject::Variant(variant),
-                    puzzles_won,
-                    puzzles_lost,
-                    average_attempts,
-                    longest_streak,
-                    current_streak,
-                };
-                all_stats.push(stats);
-            }
+    pub async fn store_puzzle(&self, puzzle: &Puzzle) -> StoreResult<i64> {
+        let row_res = self.client.query_one(
+            "
+                INSERT INTO
+                    wordle_archive.puzzles
+                    (site_id, player_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts, raw_pattern)
+                VALUES
+                    ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                RETURNING
+                    id
+            ",
+            &[
+                &puzzle.site_id, &puzzle.player_id, &puzzle.date, &puzzle.day_ordinal, &puzzle.head,
+                &puzzle.tail, &puzzle.pattern, &puzzle.solution, &puzzle.attempts, &puzzle.raw_pattern,
+            ],
+        ).await;
+        match row_res {
+            Ok(row) => StoreResult::Stored(row.get(0)),
+            Err(e) if is_duplicate_puzzle_error(&e) => StoreResult::Duplicate,
+            Err(e) => {
+                error!("failed to insert puzzle: {}", e);
+                StoreResult::Error
+            },
         }
+    }
 
-        // site stats
-        {
-            let rows_res = self.client.query(
+    /// Stores the puzzle, replacing any puzzle with the same site, date and daily ordinal.
+    pub async fn store_or_overwrite_puzzle(&self, puzzle: &Puzzle) -> Option<i64> {
+        let row_res = self.client.query_one(
+            &format!(
                 "
-                    SELECT
-                        site_id, site_name, site_css_class,
-                        puzzles_won, puzzles_lost, average_attempts, longest_streak, current_streak
-                    FROM
-                        wordle_archive.site_stats
-                    ORDER BY
-                        ordering, site_id
+                    INSERT INTO
+                        wordle_archive.puzzles
+                        (site_id, player_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts, raw_pattern)
+                    VALUES
+                        ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    ON CONFLICT ON CONSTRAINT {} DO UPDATE SET
+                        head = EXCLUDED.head,
+                        tail = EXCLUDED.tail,
+                        pattern = EXCLUDED.pattern,
+                        solution = EXCLUDED.solution,
+                        attempts = EXCLUDED.attempts,
+                        raw_pattern = EXCLUDED.raw_pattern
+                    RETURNING
+                        id
                 ",
-                &[],
-            ).await;
-            let rows = match rows_res {
-                Ok(rs) => rs,
-                Err(e) => {
-                    error!("failed to obtain statistics: {}", e);
-                    return None;
-                },
-            };
+                PUZZLE_DAY_CONSTRAINT,
+            ),
+            &[
+                &puzzle.site_id, &puzzle.player_id, &puzzle.date, &puzzle.day_ordinal, &puzzle.head,
+                &puzzle.tail, &puzzle.pattern, &puzzle.solution, &puzzle.attempts, &puzzle.raw_pattern,
+            ],
+        ).await;
+        match row_res {
+            Ok(row) => Some(row.get(0)),
+            Err(e) => {
+                error!("failed to insert or overwrite puzzle: {}", e);
+                None
+            },
+        }
+    }
 
-            for row in rows {
-                let site_id = row.get(0);
-                let site_name = row.get(1);
-                let site_css_class = row.get(2);
-                let puzzles_won = row.get(3);
-                let puzzles_lost = row.get(4);
-                let average_attempts = row.get(5);
-                let longest_streak = row.get(6);
-                let current_streak_some = row.get(7);
-                let current_streak = Some(current_streak_some);
-
-                let stats = Stats {
-                    subject: StatsSubject::Site { id: site_id, name: site_name, css_class: site_css_class },
-                    puzzles_won,
-                    puzzles_lost,
-                    average_attempts,
-                    longest_streak,
-                    current_streak,
-                };
-                all_stats.push(stats);
-            }
+    /// Returns the ID of the puzzle stored by the given player for the given site, date and daily
+    /// ordinal.
+    pub async fn get_puzzle_id_for_day(&self, site_id: i64, player_id: i64, date: NaiveDate, day_ordinal: i64) -> OptionResult<i64> {
+        let row_opt_res = self.client.query_opt(
+            "
+                SELECT id
+                FROM wordle_archive.puzzles
+                WHERE site_id = $1
+                AND player_id = $2
+                AND puzzle_date = $3
+                AND day_ordinal = $4
+            ",
+            &[&site_id, &player_id, &date, &day_ordinal],
+        ).await;
+        match row_opt_res {
+            Ok(Some(r)) => OptionResult::Present(r.get(0)),
+            Ok(None) => OptionResult::Absent,
+            Err(e) => {
+                error!("failed to obtain puzzle ID for day: {}", e);
+                OptionResult::Error
+            },
         }
+    }
 
-        Some(all_stats)
+    /// Returns the lowest daily ordinal following all puzzles stored by the given player for the
+    /// given site and date.
+    ///
+    /// If `except_puzzle_id` is given, the puzzle with that ID is not taken into account; this is
+    /// useful when an existing puzzle is being edited.
+    pub async fn get_next_free_day_ordinal(&self, site_id: i64, player_id: i64, date: NaiveDate, except_puzzle_id: Option<i64>) -> Option<i64> {
+        let row_res = self.client.query_one(
+            "
+                SELECT COALESCE(MAX(day_ordinal) + 1, 0)
+                FROM wordle_archive.puzzles
+                WHERE site_id = $1
+                AND player_id = $2
+                AND puzzle_date = $3
+                AND ($4::bigint IS NULL OR id <> $4)
+            ",
+            &[&site_id, &player_id, &date, &except_puzzle_id],
+        ).await;
+        match row_res {
+            Ok(row) => Some(row.get(0)),
+            Err(e) => {
+                error!("failed to obtain next free daily ordinal: {}", e);
+                None
+            },
+        }
     }
 
-    pub async fn store_puzzle(&self, puzzle: &Puzzle) -> bool {
-        let res = self.client.execute(
+    /// Replaces the data of an existing puzzle with that in `puzzle`, matching on `puzzle.id`.
+    pub async fn update_puzzle(&self, puzzle: &Puzzle) -> UpdateResult {
+        let modified_res = self.client.execute(
             "
-                INSERT INTO
+                UPDATE
                     wordle_archive.puzzles
-                    (site_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts, raw_pattern)
-                VALUES
-                    ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                SET
+                    site_id = $2,
+                    player_id = $3,
+                    puzzle_date = $4,
+                    day_ordinal = $5,
+                    head = $6,
+                    tail = $7,
+                    pattern = $8,
+                    solution = $9,
+                    attempts = $10,
+                    raw_pattern = $11
+                WHERE
+                    id = $1
             ",
             &[
-                &puzzle.site_id, &puzzle.date, &puzzle.day_ordinal, &puzzle.head, &puzzle.tail,
-                &puzzle.pattern, &puzzle.solution, &puzzle.attempts, &puzzle.raw_pattern,
+                &puzzle.id, &puzzle.site_id, &puzzle.player_id, &puzzle.date, &puzzle.day_ordinal,
+                &puzzle.head, &puzzle.tail, &puzzle.pattern, &puzzle.solution, &puzzle.attempts,
+                &puzzle.raw_pattern,
             ],
         ).await;
-        if let Err(e) = res {
-            error!("failed to insert puzzle: {}", e);
-            false
-        } else {
-            true
+        match modified_res {
+            Ok(0) => UpdateResult::NotFound,
+            Ok(_) => UpdateResult::Updated,
+            Err(e) if is_duplicate_puzzle_error(&e) => UpdateResult::Duplicate,
+            Err(e) => {
+                error!("failed to update puzzle: {}", e);
+                UpdateResult::Error
+            },
+        }
+    }
+
+    /// Replaces the data of multiple existing puzzles, matching on their IDs. Either all puzzles
+    /// are updated or none are.
+    pub async fn update_puzzles_atomically(&mut self, puzzles: &[Puzzle]) -> bool {
+        let txn = match self.client.transaction().await {
+            Ok(t) => t,
+            Err(e) => {
+                error!("failed to start transaction: {}", e);
+                return false;
+            },
+        };
+        for puzzle in puzzles {
+            let modified_res = txn.execute(
+                "
+                    UPDATE
+                        wordle_archive.puzzles
+                    SET
+                        head = $2,
+                        tail = $3,
+                        pattern = $4,
+                        solution = $5,
+                        attempts = $6,
+                        raw_pattern = $7
+                    WHERE
+                        id = $1
+                ",
+                &[
+                    &puzzle.id, &puzzle.head, &puzzle.tail, &puzzle.pattern, &puzzle.solution,
+                    &puzzle.attempts, &puzzle.raw_pattern,
+                ],
+            ).await;
+            match modified_res {
+                Ok(1) => {},
+                Ok(n) => {
+                    error!("updating puzzle {} modified {} rows; rolling back", puzzle.id, n);
+                    return false;
+                },
+                Err(e) => {
+                    error!("failed to update puzzle {}; rolling back: {}", puzzle.id, e);
+                    return false;
+                },
+            }
+        }
+        if let Err(e) = txn.commit().await {
+            error!("failed to commit transaction: {}", e);
+            return false;
+        }
+        true
+    }
+
+    pub async fn delete_puzzle(&self, id: i64) -> OptionResult<()> {
+        let deleted_res = self.client.execute(
+            "DELETE FROM wordle_archive.puzzles WHERE id = $1",
+            &[&id],
+        ).await;
+        match deleted_res {
+            Ok(0) => OptionResult::Absent,
+            Ok(_) => OptionResult::Present(()),
+            Err(e) => {
+                error!("failed to delete puzzle: {}", e);
+                OptionResult::Error
+            },
         }
     }
 }
@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use rusqlite::DatabaseName;
+use serde::{Deserialize, Serialize};
+
+use crate::model::{Player, PuzzleSite, SiteAndPuzzle};
+
+
+/// A single puzzle along with the site it belongs to, as written by the export and read by the
+/// import.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub(crate) struct ExportRecord {
+    pub site_id: i64,
+    pub site_name: String,
+    pub site_url: String,
+    pub site_css_class: String,
+    pub site_variant: String,
+    pub player_name: String,
+    pub puzzle_id: i64,
+    pub puzzle_date: NaiveDate,
+    pub day_ordinal: i64,
+    pub head: String,
+    pub tail: String,
+    pub pattern: String,
+    pub solution: String,
+    pub attempts: Option<i64>,
+    pub raw_pattern: Option<String>,
+}
+impl ExportRecord {
+    pub fn new(db_puzzle: &SiteAndPuzzle, player_name: &str) -> Self {
+        Self {
+            site_id: db_puzzle.site.id,
+            site_name: db_puzzle.site.name.clone(),
+            site_url: db_puzzle.site.url.clone(),
+            site_css_class: db_puzzle.site.css_class.clone(),
+            site_variant: db_puzzle.site.variant.clone(),
+            player_name: player_name.to_owned(),
+            puzzle_id: db_puzzle.puzzle.id,
+            puzzle_date: db_puzzle.puzzle.date,
+            day_ordinal: db_puzzle.puzzle.day_ordinal,
+            head: db_puzzle.puzzle.head.clone(),
+            tail: db_puzzle.puzzle.tail.clone(),
+            pattern: db_puzzle.puzzle.pattern.clone(),
+            solution: db_puzzle.puzzle.solution.clone(),
+            attempts: db_puzzle.puzzle.attempts,
+            raw_pattern: db_puzzle.puzzle.raw_pattern.clone(),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ValueEnum)]
+pub(crate) enum ExportFormat {
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+    /// A SQLite database containing the tables `sites` and `puzzles`.
+    Sqlite,
+}
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "application/jsonl",
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Sqlite => "application/vnd.sqlite3",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Csv => "csv",
+            Self::Sqlite => "sqlite",
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub(crate) enum ExportError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Csv(csv::Error),
+    Sqlite(rusqlite::Error),
+}
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "I/O error: {}", e),
+            Self::Json(e) => write!(f, "JSON error: {}", e),
+            Self::Csv(e) => write!(f, "CSV error: {}", e),
+            Self::Sqlite(e) => write!(f, "SQLite error: {}", e),
+        }
+    }
+}
+impl std::error::Error for ExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Json(e) => Some(e),
+            Self::Csv(e) => Some(e),
+            Self::Sqlite(e) => Some(e),
+        }
+    }
+}
+impl From<io::Error> for ExportError {
+    fn from(value: io::Error) -> Self { Self::Io(value) }
+}
+impl From<serde_json::Error> for ExportError {
+    fn from(value: serde_json::Error) -> Self { Self::Json(value) }
+}
+impl From<csv::Error> for ExportError {
+    fn from(value: csv::Error) -> Self { Self::Csv(value) }
+}
+impl From<rusqlite::Error> for ExportError {
+    fn from(value: rusqlite::Error) -> Self { Self::Sqlite(value) }
+}
+
+
+/// Encodes puzzles one at a time in the JSON Lines or CSV format, so that an export can be written
+/// while the puzzles are still being read from the database.
+pub(crate) struct RecordEncoder {
+    format: ExportFormat,
+    player_names: HashMap<i64, String>,
+    /// Whether the CSV header row has already been encoded.
+    csv_header_written: bool,
+}
+impl RecordEncoder {
+    /// Returns an encoder for the given format, or `None` for the SQLite format, which can only be
+    /// written as a whole.
+    pub fn new(format: ExportFormat, players: &[Player]) -> Option<Self> {
+        if format == ExportFormat::Sqlite {
+            return None;
+        }
+        Some(Self {
+            format,
+            player_names: player_id_to_name(players),
+            csv_header_written: false,
+        })
+    }
+
+    /// Encodes a single puzzle. When encoding CSV, the header row precedes the first puzzle.
+    pub fn encode(&mut self, db_puzzle: &SiteAndPuzzle) -> Result<Vec<u8>, ExportError> {
+        let player_name = self.player_names.get(&db_puzzle.puzzle.player_id)
+            .map(|n| n.as_str())
+            .unwrap_or_default();
+        let record = ExportRecord::new(db_puzzle, player_name);
+        if self.format == ExportFormat::Csv {
+            let mut csv_writer = csv::WriterBuilder::new()
+                .has_headers(!self.csv_header_written)
+                .from_writer(Vec::new());
+            csv_writer.serialize(&record)?;
+            self.csv_header_written = true;
+            csv_writer.into_inner()
+                .map_err(|e| ExportError::Io(e.into_error()))
+        } else {
+            let mut line = serde_json::to_vec(&record)?;
+            line.push(b'\n');
+            Ok(line)
+        }
+    }
+}
+
+
+/// Writes the sites and puzzles in the given format.
+///
+/// The JSON Lines and CSV formats only contain sites and players that have at least one puzzle;
+/// the SQLite format contains all sites and players.
+pub(crate) fn write_export<W: Write>(
+    format: ExportFormat,
+    sites: &[PuzzleSite],
+    players: &[Player],
+    puzzles: &[SiteAndPuzzle],
+    mut writer: W,
+) -> Result<(), ExportError> {
+    let mut encoder = match RecordEncoder::new(format, players) {
+        Some(e) => e,
+        None => return write_sqlite(sites, players, puzzles, writer),
+    };
+    for puzzle in puzzles {
+        writer.write_all(&encoder.encode(puzzle)?)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn player_id_to_name(players: &[Player]) -> HashMap<i64, String> {
+    players.iter()
+        .map(|p| (p.id, p.name.clone()))
+        .collect()
+}
+
+fn write_sqlite<W: Write>(sites: &[PuzzleSite], players: &[Player], puzzles: &[SiteAndPuzzle], mut writer: W) -> Result<(), ExportError> {
+    let mut conn = rusqlite::Connection::open_in_memory()?;
+    conn.execute_batch(include_str!("../db/export-sqlite.sql"))?;
+
+    {
+        let txn = conn.transaction()?;
+        {
+            let mut site_stmt = txn.prepare(
+                "
+                    INSERT INTO sites
+                        (id, name, url, css_class, variant, notes, available, max_attempts, streak_max_gap_days)
+                    VALUES
+                        (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                ",
+            )?;
+            for site in sites {
+                site_stmt.execute(rusqlite::params![
+                    site.id, site.name, site.url, site.css_class, site.variant, site.notes,
+                    site.available, site.max_attempts, site.streak_max_gap_days,
+                ])?;
+            }
+
+            let mut player_stmt = txn.prepare(
+                "INSERT INTO players (id, name) VALUES (?1, ?2)",
+            )?;
+            for player in players {
+                player_stmt.execute(rusqlite::params![player.id, player.name])?;
+            }
+
+            let mut puzzle_stmt = txn.prepare(
+                "
+                    INSERT INTO puzzles
+                        (id, site_id, player_id, puzzle_date, day_ordinal, head, tail, pattern, solution, attempts, raw_pattern)
+                    VALUES
+                        (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                ",
+            )?;
+            for db_puzzle in puzzles {
+                let puzzle = &db_puzzle.puzzle;
+                puzzle_stmt.execute(rusqlite::params![
+                    puzzle.id, puzzle.site_id, puzzle.player_id, puzzle.date, puzzle.day_ordinal,
+                    puzzle.head, puzzle.tail, puzzle.pattern, puzzle.solution, puzzle.attempts,
+                    puzzle.raw_pattern,
+                ])?;
+            }
+        }
+        txn.commit()?;
+    }
+
+    let data = conn.serialize(DatabaseName::Main)?;
+    writer.write_all(&data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::import::{ImportFormat, ImportRecord, read_records};
+    use crate::model::Puzzle;
+    use crate::parsers::test_utils::test_site;
+
+    fn players() -> Vec<Player> {
+        vec![
+            Player { id: 1, name: "default".to_owned() },
+            Player { id: 2, name: "alice".to_owned() },
+        ]
+    }
+
+    fn sites() -> Vec<PuzzleSite> {
+        let mut unplayed = test_site("globle", "globle");
+        unplayed.id = 2;
+        unplayed.name = "Unplayed".to_owned();
+        unplayed.url = "https://unplayed.example.com/".to_owned();
+        unplayed.max_attempts = Some(6);
+        vec![test_site("wordle", "wordle"), unplayed]
+    }
+
+    fn puzzles() -> Vec<SiteAndPuzzle> {
+        let site = test_site("wordle", "wordle");
+        vec![
+            SiteAndPuzzle {
+                site: site.clone(),
+                puzzle: Puzzle {
+                    id: 10,
+                    site_id: site.id,
+                    player_id: 1,
+                    date: NaiveDate::from_ymd_opt(2022, 6, 16).unwrap(),
+                    day_ordinal: 0,
+                    head: "Test 1, \"hard\"\n\n".to_owned(),
+                    tail: "\nend".to_owned(),
+                    pattern: "WM\nCC".to_owned(),
+                    solution: "AB\nCD".to_owned(),
+                    attempts: Some(2),
+                    raw_pattern: Some("\u{2B1C}\u{1F7E8}\n\u{1F7E9}\u{1F7E9}".to_owned()),
+                },
+            },
+            SiteAndPuzzle {
+                site: site.clone(),
+                puzzle: Puzzle {
+                    id: 11,
+                    site_id: site.id,
+                    player_id: 2,
+                    date: NaiveDate::from_ymd_opt(2022, 6, 17).unwrap(),
+                    day_ordinal: 1,
+                    head: String::new(),
+                    tail: String::new(),
+                    pattern: "WW".to_owned(),
+                    solution: "AB\nCD".to_owned(),
+                    attempts: None,
+                    raw_pattern: None,
+                },
+            },
+        ]
+    }
+
+    fn export(format: ExportFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_export(format, &sites(), &players(), &puzzles(), &mut data).unwrap();
+        data
+    }
+
+    fn assert_round_trip(format: ExportFormat, import_format: ImportFormat) {
+        let data = export(format);
+        let records: Vec<ImportRecord> = read_records(import_format, data.as_slice())
+            .into_iter()
+            .map(|(_line_number, record_res)| record_res.unwrap())
+            .collect();
+        let puzzles = puzzles();
+        assert_eq!(records.len(), puzzles.len());
+        for ((record, db_puzzle), player_name) in records.iter().zip(puzzles.iter()).zip(["default", "alice"]) {
+            let puzzle = &db_puzzle.puzzle;
+            assert_eq!(record.site_name.as_deref(), Some(db_puzzle.site.name.as_str()));
+            assert_eq!(record.site_url.as_deref(), Some(db_puzzle.site.url.as_str()));
+            assert_eq!(record.player_name.as_deref(), Some(player_name));
+            assert_eq!(record.puzzle_date, puzzle.date);
+            assert_eq!(record.day_ordinal, puzzle.day_ordinal);
+            assert_eq!(record.head, puzzle.head);
+            assert_eq!(record.tail, puzzle.tail);
+            assert_eq!(record.pattern.as_deref(), Some(puzzle.pattern.as_str()));
+            assert_eq!(record.solution, puzzle.solution);
+            assert_eq!(record.attempts, puzzle.attempts);
+            assert_eq!(record.raw_pattern, puzzle.raw_pattern);
+        }
+    }
+
+    #[test]
+    fn test_player_id_to_name() {
+        let player_names = player_id_to_name(&players());
+        assert_eq!(player_names.get(&1).map(|n| n.as_str()), Some("default"));
+        assert_eq!(player_names.get(&2).map(|n| n.as_str()), Some("alice"));
+        assert_eq!(player_names.get(&3), None);
+    }
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        assert_round_trip(ExportFormat::Jsonl, ImportFormat::Jsonl);
+        let data = String::from_utf8(export(ExportFormat::Jsonl)).unwrap();
+        assert_eq!(data.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        assert_round_trip(ExportFormat::Csv, ImportFormat::Csv);
+        let data = String::from_utf8(export(ExportFormat::Csv)).unwrap();
+        assert_eq!(data.matches("site_id,").count(), 1);
+    }
+
+    #[test]
+    fn test_encoder() {
+        assert!(RecordEncoder::new(ExportFormat::Sqlite, &players()).is_none());
+
+        let mut encoder = RecordEncoder::new(ExportFormat::Csv, &players()).unwrap();
+        let puzzles = puzzles();
+        let first = String::from_utf8(encoder.encode(&puzzles[0]).unwrap()).unwrap();
+        let second = String::from_utf8(encoder.encode(&puzzles[1]).unwrap()).unwrap();
+        assert!(first.starts_with("site_id,site_name,"));
+        assert!(second.starts_with("1,Test,"));
+        assert!(second.contains(",alice,11,2022-06-17,1,"));
+    }
+
+    #[test]
+    fn test_sqlite_contains_all_sites() {
+        let data = export(ExportFormat::Sqlite);
+        let path = std::env::temp_dir().join(format!("wordle-archive-export-test-{}.sqlite", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let conn = rusqlite::Connection::open(&path).unwrap();
+
+        let site_names: Vec<String> = conn.prepare("SELECT name FROM sites ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert_eq!(site_names, ["Test", "Unplayed"]);
+        let unplayed_max_attempts: Option<i64> = conn.query_row("SELECT max_attempts FROM sites WHERE id = 2", [], |row| row.get(0)).unwrap();
+        assert_eq!(unplayed_max_attempts, Some(6));
+
+        let player_count: i64 = conn.query_row("SELECT COUNT(*) FROM players", [], |row| row.get(0)).unwrap();
+        assert_eq!(player_count, 2);
+
+        let puzzle_count: i64 = conn.query_row("SELECT COUNT(*) FROM puzzles", [], |row| row.get(0)).unwrap();
+        assert_eq!(puzzle_count, 2);
+        let head: String = conn.query_row("SELECT head FROM puzzles WHERE id = 10", [], |row| row.get(0)).unwrap();
+        assert_eq!(head, "Test 1, \"hard\"\n\n");
+        let (player_id, attempts, raw_pattern): (i64, Option<i64>, Option<String>) = conn.query_row(
+            "SELECT player_id, attempts, raw_pattern FROM puzzles WHERE id = 11",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!((player_id, attempts, raw_pattern), (2, None, None));
+
+        drop(conn);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -2,9 +2,8 @@ use std::cell::RefCell;
 use std::fmt::Write;
 use std::ops::Range;
 
-use askama;
-
 use crate::{PuzzlePart, SubPuzzle};
+use crate::parsers::parser_for_variant;
 
 
 pub(crate) struct WrongSolutionManager {
@@ -49,7 +48,7 @@ pub(crate) fn jsstring<S: AsRef<str>>(string: S) -> askama::Result<String> {
             '<' => ret.push_str("\\u003C"),
             '>' => ret.push_str("\\u003E"),
             other => {
-                if other >= ' ' && other <= '~' {
+                if (' '..='~').contains(&other) {
                     ret.push(other);
                 } else {
                     // pre-encode to UTF-16 for maximum compatibility
@@ -71,7 +70,7 @@ pub(crate) fn puzzle_string(puzzle_part: &PuzzlePart) -> askama::Result<String>
     if let Some(raw_guesses) = &puzzle_part.raw_guesses {
         let mut ret = String::new();
         ret.push_str(&puzzle_part.head);
-        ret.push_str(&raw_guesses);
+        ret.push_str(raw_guesses);
         ret.push_str(&puzzle_part.tail);
         return Ok(ret);
     }
@@ -79,45 +78,19 @@ pub(crate) fn puzzle_string(puzzle_part: &PuzzlePart) -> askama::Result<String>
     // attempt to reconstruct
     let mut ret = String::new();
     ret.push_str(&puzzle_part.head);
-
-    let correct_square = '\u{1F7E9}';
-    let misplaced_square = if puzzle_part.site.css_class == "nerdle" { '\u{1F7EA}' } else { '\u{1F7E8}' };
-    let wrong_square = '\u{2B1C}'; // or \u{2B1B} in dark mode
-    for (j, sub_puzzle) in puzzle_part.sub_puzzles.iter().enumerate() {
-        if j > 0 {
-            ret.push_str("\n\n");
-        }
-
-        for (i, (guess, _solution)) in sub_puzzle.guess_lines.iter().enumerate() {
-            for row_char in guess.chars() {
-                if row_char == 'C' {
-                    ret.push(correct_square);
-                } else if row_char == 'M' {
-                    ret.push(misplaced_square);
-                } else if row_char == 'W' {
-                    ret.push(wrong_square);
-                } else if puzzle_part.site.variant == "geo" {
-                    // probably the arrow behind the squares
-                    ret.push(row_char);
-                    // append the emoji variation selector
-                    ret.push('\u{FE0F}');
-                }
-            }
-
-            if puzzle_part.site.variant != "audio" && i < sub_puzzle.guess_lines.len()-1 {
-                ret.push('\n');
-            }
-        }
-    }
-
+    ret.push_str(&parser_for_variant(&puzzle_part.site.variant).reconstruct_guesses(puzzle_part));
     ret.push_str(&puzzle_part.tail);
     Ok(ret)
 }
 
-pub(crate) fn get_index<'t, 'i, T>(slice: &'t [T], index: &'i usize) -> askama::Result<Option<&'t T>> {
+pub(crate) fn get_index<'t, T>(slice: &'t [T], index: &usize) -> askama::Result<Option<&'t T>> {
     Ok(slice.get(*index))
 }
 
+pub(crate) fn parse_index<S: AsRef<str>>(string: S) -> askama::Result<Option<usize>> {
+    Ok(string.as_ref().parse().ok())
+}
+
 pub(crate) fn make_wrong_solution_manager(sub_puzzle: &SubPuzzle) -> askama::Result<WrongSolutionManager> {
     // calculate how many wrong guesses we have
     let wrong_guesses = sub_puzzle.pattern_lines
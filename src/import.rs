@@ -0,0 +1,412 @@
+use std::io::{BufRead, Read};
+
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::db_puzzle_to_puzzle_part;
+use crate::model::{Player, Puzzle, PuzzleSite, SiteAndPuzzle};
+use crate::parsers::{parse_puzzle_data, parser_for_variant};
+
+
+/// A puzzle to import.
+///
+/// Follows the format of [`ExportRecord`](crate::export::ExportRecord), but only the date, the
+/// solution, one of the site name or URL and either the raw pattern or the pattern are required.
+/// IDs are ignored. Puzzles without a player name are assigned to the player chosen when
+/// importing.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub(crate) struct ImportRecord {
+    #[serde(default)] pub site_name: Option<String>,
+    #[serde(default)] pub site_url: Option<String>,
+    #[serde(default)] pub player_name: Option<String>,
+    pub puzzle_date: NaiveDate,
+    #[serde(default)] pub day_ordinal: i64,
+    #[serde(default)] pub head: String,
+    #[serde(default)] pub tail: String,
+    #[serde(default)] pub pattern: Option<String>,
+    pub solution: String,
+    #[serde(default)] pub attempts: Option<i64>,
+    #[serde(default)] pub raw_pattern: Option<String>,
+}
+
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, ValueEnum)]
+pub(crate) enum ImportFormat {
+    /// One JSON object per line.
+    Jsonl,
+    /// Comma-separated values with a header row.
+    Csv,
+}
+
+
+/// Reads the records from the given reader. Each record is returned along with its line number.
+///
+/// Records that cannot be decoded are returned as errors; reading continues with the next record.
+pub(crate) fn read_records<R: BufRead>(format: ImportFormat, reader: R) -> Vec<(u64, Result<ImportRecord, String>)> {
+    match format {
+        ImportFormat::Jsonl => read_jsonl(reader),
+        ImportFormat::Csv => read_csv(reader),
+    }
+}
+
+fn read_jsonl<R: BufRead>(reader: R) -> Vec<(u64, Result<ImportRecord, String>)> {
+    let mut records = Vec::new();
+    for (i, line_res) in reader.lines().enumerate() {
+        let line_number = (i + 1) as u64;
+        let line = match line_res {
+            Ok(l) => l,
+            Err(e) => {
+                records.push((line_number, Err(format!("failed to read line: {}", e))));
+                break;
+            },
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record_res = serde_json::from_str(&line)
+            .map_err(|e| format!("failed to decode record: {}", e));
+        records.push((line_number, record_res));
+    }
+    records
+}
+
+fn read_csv<R: Read>(reader: R) -> Vec<(u64, Result<ImportRecord, String>)> {
+    let mut records = Vec::new();
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let headers = match csv_reader.headers() {
+        Ok(h) => h.clone(),
+        Err(e) => {
+            records.push((1, Err(format!("failed to read header row: {}", e))));
+            return records;
+        },
+    };
+    for string_record_res in csv_reader.records() {
+        match string_record_res {
+            Ok(string_record) => {
+                let line_number = string_record.position()
+                    .map(|p| p.line())
+                    .unwrap_or(0);
+                let record_res = string_record.deserialize(Some(&headers))
+                    .map_err(|e| format!("failed to decode record: {}", e));
+                records.push((line_number, record_res));
+            },
+            Err(e) => {
+                let line_number = e.position()
+                    .map(|p| p.line())
+                    .unwrap_or(0);
+                let fatal = e.is_io_error();
+                records.push((line_number, Err(format!("failed to read record: {}", e))));
+                if fatal {
+                    break;
+                }
+            },
+        }
+    }
+    records
+}
+
+
+/// Finds the site referenced by the record, by name or by URL.
+pub(crate) fn find_site<'s>(record: &ImportRecord, sites: &'s [PuzzleSite]) -> Result<&'s PuzzleSite, String> {
+    if let Some(name) = &record.site_name {
+        if let Some(site) = sites.iter().find(|s| &s.name == name) {
+            return Ok(site);
+        }
+    }
+    if let Some(url) = &record.site_url {
+        if let Some(site) = sites.iter().find(|s| &s.url == url) {
+            return Ok(site);
+        }
+    }
+    match (&record.site_name, &record.site_url) {
+        (None, None) => Err("neither site_name nor site_url given".to_owned()),
+        (Some(name), None) => Err(format!("no site named {:?}", name)),
+        (None, Some(url)) => Err(format!("no site with URL {:?}", url)),
+        (Some(name), Some(url)) => Err(format!("no site named {:?} or with URL {:?}", name, url)),
+    }
+}
+
+/// Finds the player referenced by the record, falling back to `default_player` if the record does
+/// not name one.
+pub(crate) fn find_player<'p>(record: &ImportRecord, players: &'p [Player], default_player: &'p Player) -> Result<&'p Player, String> {
+    match &record.player_name {
+        Some(name) => players.iter()
+            .find(|p| &p.name == name)
+            .ok_or_else(|| format!("no player named {:?}", name)),
+        None => Ok(default_player),
+    }
+}
+
+/// Ensures that there are at least as many guesses as sub-puzzles, since the last guesses are taken
+/// as the solutions of the sub-puzzles.
+pub(crate) fn check_guess_count(pattern: &str, solution: &str) -> Result<(), String> {
+    if solution.split('\n').count() < pattern.split("\n\n").count() {
+        Err("fewer guesses than sub-puzzles".to_owned())
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts the record into a puzzle of the given site and player.
+///
+/// Unless `trust_parsed` is set, the result is assembled from the head, the raw pattern (or, if it
+/// is missing, a pattern reconstructed from the decoded pattern) and the tail and parsed again
+/// using the parser for the site's variant. If `trust_parsed` is set, the decoded pattern and the
+/// number of attempts are taken over verbatim.
+pub(crate) fn record_to_puzzle(record: &ImportRecord, site: &PuzzleSite, player: &Player, trust_parsed: bool) -> Result<Puzzle, String> {
+    let solution = record.solution.replace('\r', "");
+
+    if trust_parsed {
+        let pattern = match &record.pattern {
+            Some(p) => p.replace('\r', ""),
+            None => return Err("pattern is required when trusting parsed data".to_owned()),
+        };
+        check_guess_count(&pattern, &solution)?;
+        return Ok(Puzzle {
+            id: -1,
+            site_id: site.id,
+            player_id: player.id,
+            date: record.puzzle_date,
+            day_ordinal: record.day_ordinal,
+            head: record.head.replace('\r', ""),
+            tail: record.tail.replace('\r', ""),
+            pattern,
+            solution,
+            attempts: record.attempts,
+            raw_pattern: record.raw_pattern.as_ref().map(|rp| rp.replace('\r', "")),
+        });
+    }
+
+    let raw_pattern = match (&record.raw_pattern, &record.pattern) {
+        (Some(rp), _) => rp.replace('\r', ""),
+        (None, Some(pattern)) => {
+            let pattern = pattern.replace('\r', "");
+            check_guess_count(&pattern, &solution)?;
+            let site_and_puzzle = SiteAndPuzzle {
+                site: site.clone(),
+                puzzle: Puzzle {
+                    id: -1,
+                    site_id: site.id,
+                    player_id: player.id,
+                    date: record.puzzle_date,
+                    day_ordinal: record.day_ordinal,
+                    head: String::new(),
+                    tail: String::new(),
+                    pattern,
+                    solution: solution.clone(),
+                    attempts: record.attempts,
+                    raw_pattern: None,
+                },
+            };
+            let puzzle_part = db_puzzle_to_puzzle_part(&site_and_puzzle);
+            parser_for_variant(&site.variant).reconstruct_guesses(&puzzle_part)
+        },
+        (None, None) => return Err("neither raw_pattern nor pattern given".to_owned()),
+    };
+
+    let mut result = String::new();
+    result.push_str(&record.head.replace('\r', ""));
+    result.push_str(&raw_pattern);
+    result.push_str(&record.tail.replace('\r', ""));
+
+    let puzzle_data = parse_puzzle_data(site, &result, &solution)
+        .map_err(|e| e.to_string())?;
+    Ok(puzzle_data.into_puzzle(site.id, player.id, record.puzzle_date, record.day_ordinal))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::test_site;
+
+    const CORRECT: &str = "\u{1F7E9}";
+    const MISPLACED: &str = "\u{1F7E8}";
+    const WRONG: &str = "\u{2B1C}";
+
+    fn player() -> Player {
+        Player { id: 2, name: "alice".to_owned() }
+    }
+
+    fn record(pattern: Option<&str>, raw_pattern: Option<&str>, solution: &str) -> ImportRecord {
+        ImportRecord {
+            site_name: Some("Test".to_owned()),
+            site_url: None,
+            player_name: None,
+            puzzle_date: NaiveDate::from_ymd_opt(2022, 6, 16).unwrap(),
+            day_ordinal: 1,
+            head: "Test 1 2/6\n\n".to_owned(),
+            tail: String::new(),
+            pattern: pattern.map(|p| p.to_owned()),
+            solution: solution.to_owned(),
+            attempts: Some(2),
+            raw_pattern: raw_pattern.map(|rp| rp.to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_trust_parsed() {
+        let site = test_site("wordle", "wordle");
+        let mut rec = record(Some("WM\r\nCC"), None, "AB\r\nCD");
+        // attempts are taken over even if they do not match the pattern
+        rec.attempts = Some(5);
+        let puzzle = record_to_puzzle(&rec, &site, &player(), true).unwrap();
+        assert_eq!(puzzle.site_id, site.id);
+        assert_eq!(puzzle.player_id, 2);
+        assert_eq!(puzzle.day_ordinal, 1);
+        assert_eq!(puzzle.pattern, "WM\nCC");
+        assert_eq!(puzzle.solution, "AB\nCD");
+        assert_eq!(puzzle.attempts, Some(5));
+        assert_eq!(puzzle.raw_pattern, None);
+    }
+
+    #[test]
+    fn test_trust_parsed_requires_pattern() {
+        let site = test_site("wordle", "wordle");
+        let rec = record(None, Some(CORRECT), "AB");
+        assert!(record_to_puzzle(&rec, &site, &player(), true).is_err());
+    }
+
+    #[test]
+    fn test_fewer_guesses_than_sub_puzzles() {
+        let site = test_site("wordle", "wordle");
+        let rec = record(Some("CC\n\nWW\nCC"), None, "AB");
+        assert_eq!(
+            record_to_puzzle(&rec, &site, &player(), true),
+            Err("fewer guesses than sub-puzzles".to_owned()),
+        );
+        assert_eq!(
+            record_to_puzzle(&rec, &site, &player(), false),
+            Err("fewer guesses than sub-puzzles".to_owned()),
+        );
+    }
+
+    #[test]
+    fn test_malformed_puzzle_can_be_displayed() {
+        // puzzles stored before the guess count was checked must not break the page
+        let site = test_site("wordle", "wordle");
+        let mut puzzle = record_to_puzzle(&record(Some("CC"), None, "AB"), &site, &player(), true).unwrap();
+        puzzle.pattern = "CC\n\nWW\nCC".to_owned();
+        let puzzle_part = db_puzzle_to_puzzle_part(&SiteAndPuzzle { site, puzzle });
+        assert_eq!(puzzle_part.sub_puzzles.len(), 2);
+        assert_eq!(puzzle_part.sub_puzzles[0].solution, "");
+        assert_eq!(puzzle_part.sub_puzzles[1].solution, "AB");
+    }
+
+    #[test]
+    fn test_reparse_raw_pattern() {
+        let site = test_site("wordle", "wordle");
+        let raw_pattern = format!("{}{}\n{}{}", WRONG, MISPLACED, CORRECT, CORRECT);
+        // the stored pattern is ignored in favor of the raw pattern
+        let rec = record(Some("CC\nCC"), Some(&raw_pattern), "AB\nCD");
+        let puzzle = record_to_puzzle(&rec, &site, &player(), false).unwrap();
+        assert_eq!(puzzle.head, "Test 1 2/6\n\n");
+        assert_eq!(puzzle.pattern, "WM\nCC");
+        assert_eq!(puzzle.attempts, Some(2));
+        assert_eq!(puzzle.raw_pattern, Some(raw_pattern));
+    }
+
+    #[test]
+    fn test_reparse_reconstructs_raw_pattern() {
+        let site = test_site("wordle", "wordle");
+        let rec = record(Some("WM\nCC"), None, "AB\nCD");
+        let puzzle = record_to_puzzle(&rec, &site, &player(), false).unwrap();
+        assert_eq!(puzzle.pattern, "WM\nCC");
+        assert_eq!(puzzle.attempts, Some(2));
+        assert_eq!(puzzle.raw_pattern, Some(format!("{}{}\n{}{}", WRONG, MISPLACED, CORRECT, CORRECT)));
+    }
+
+    #[test]
+    fn test_reparse_requires_pattern() {
+        let site = test_site("wordle", "wordle");
+        let rec = record(None, None, "AB");
+        assert_eq!(
+            record_to_puzzle(&rec, &site, &player(), false),
+            Err("neither raw_pattern nor pattern given".to_owned()),
+        );
+    }
+
+    #[test]
+    fn test_read_jsonl() {
+        let input = concat!(
+            "{\"site_name\": \"Test\", \"puzzle_date\": \"2022-06-16\", \"solution\": \"AB\", \"pattern\": \"CC\", \"attempts\": null, \"raw_pattern\": null}\n",
+            "\n",
+            "{\"site_url\": \"https://example.com/\", \"puzzle_date\": \"not a date\", \"solution\": \"AB\"}\n",
+            "{\"site_url\": \"https://example.com/\", \"puzzle_date\": \"2022-06-17\", \"solution\": \"CD\", \"day_ordinal\": 2}\n",
+        );
+        let records = read_records(ImportFormat::Jsonl, input.as_bytes());
+        assert_eq!(records.len(), 3);
+
+        let (line_number, first) = &records[0];
+        assert_eq!(*line_number, 1);
+        let first = first.as_ref().unwrap();
+        assert_eq!(first.site_name.as_deref(), Some("Test"));
+        assert_eq!(first.site_url, None);
+        assert_eq!(first.attempts, None);
+        assert_eq!(first.raw_pattern, None);
+        assert_eq!(first.day_ordinal, 0);
+
+        assert_eq!(records[1].0, 3);
+        assert!(records[1].1.is_err());
+
+        assert_eq!(records[2].0, 4);
+        assert_eq!(records[2].1.as_ref().unwrap().day_ordinal, 2);
+    }
+
+    #[test]
+    fn test_read_csv() {
+        let input = concat!(
+            "site_name,site_url,player_name,puzzle_date,day_ordinal,head,tail,pattern,solution,attempts,raw_pattern\n",
+            "Test,,,2022-06-16,0,,,CC,AB,,\n",
+            "Test,,bob,2022-06-17,0,,,CC,AB,many,\n",
+            "\"Test\",,,2022-06-18,1,\"Test 1\n\n\",,CC,AB,1,\n",
+        );
+        let records = read_records(ImportFormat::Csv, input.as_bytes());
+        assert_eq!(records.len(), 3);
+
+        let (line_number, first) = &records[0];
+        assert_eq!(*line_number, 2);
+        let first = first.as_ref().unwrap();
+        assert_eq!(first.site_url, None);
+        assert_eq!(first.player_name, None);
+        assert_eq!(first.attempts, None);
+        assert_eq!(first.raw_pattern, None);
+
+        assert_eq!(records[1].0, 3);
+        assert!(records[1].1.is_err());
+
+        assert_eq!(records[2].0, 4);
+        let third = records[2].1.as_ref().unwrap();
+        assert_eq!(third.head, "Test 1\n\n");
+        assert_eq!(third.attempts, Some(1));
+    }
+
+    #[test]
+    fn test_find_site() {
+        let mut other_site = test_site("globle", "globle");
+        other_site.id = 2;
+        other_site.name = "Other".to_owned();
+        other_site.url = "https://other.example.com/".to_owned();
+        let sites = vec![test_site("wordle", "wordle"), other_site];
+
+        let mut rec = record(None, None, "AB");
+        rec.site_name = Some("Other".to_owned());
+        assert_eq!(find_site(&rec, &sites).map(|s| s.id), Ok(2));
+
+        rec.site_name = None;
+        rec.site_url = Some("https://example.com/".to_owned());
+        assert_eq!(find_site(&rec, &sites).map(|s| s.id), Ok(1));
+
+        // an unknown name falls back to the URL
+        rec.site_name = Some("Renamed".to_owned());
+        assert_eq!(find_site(&rec, &sites).map(|s| s.id), Ok(1));
+
+        rec.site_url = Some("https://unknown.example.com/".to_owned());
+        assert!(find_site(&rec, &sites).is_err());
+
+        rec.site_name = None;
+        rec.site_url = None;
+        assert_eq!(find_site(&rec, &sites).map(|s| s.id), Err("neither site_name nor site_url given".to_owned()));
+    }
+}
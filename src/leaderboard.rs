@@ -0,0 +1,171 @@
+//! Comparison of the results of several players, either on the same puzzle or over a period.
+
+
+use std::cmp::Ordering;
+
+use crate::model::{Stats, StatsSubject};
+use crate::stats::is_victory;
+
+
+/// An item of a player, along with its rank among the items of the other players.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Ranked<T> {
+    /// The rank, starting at 1. Items that compare equal share the same rank, and the following
+    /// ranks are skipped accordingly (e.g. 1, 2, 2, 4).
+    pub rank: usize,
+    pub player_name: String,
+    pub item: T,
+}
+
+
+/// Ranks the items of the players, best first, according to the given comparison (which orders
+/// better items before worse ones). Items that compare equal are ordered by player name.
+pub(crate) fn rank_by<T, F: Fn(&T, &T) -> Ordering>(mut items: Vec<(String, T)>, compare: F) -> Vec<Ranked<T>> {
+    items.sort_by(|(name_a, a), (name_b, b)| compare(a, b).then_with(|| name_a.cmp(name_b)));
+
+    let mut ranked: Vec<Ranked<T>> = Vec::with_capacity(items.len());
+    for (index, (player_name, item)) in items.into_iter().enumerate() {
+        let rank = match ranked.last() {
+            Some(previous) if compare(&previous.item, &item) == Ordering::Equal => previous.rank,
+            _ => index + 1,
+        };
+        ranked.push(Ranked { rank, player_name, item });
+    }
+    ranked
+}
+
+
+/// Compares the outcomes of two players on the same puzzle: victories rank before defeats, and
+/// among victories, fewer attempts rank first.
+pub(crate) fn compare_attempts(a: Option<i64>, b: Option<i64>, max_attempts: Option<i64>) -> Ordering {
+    let key = |attempts: Option<i64>| if is_victory(attempts, max_attempts) {
+        (0, attempts.unwrap())
+    } else {
+        (1, 0)
+    };
+    key(a).cmp(&key(b))
+}
+
+
+/// The results of a player within a period.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct PlayerSummary {
+    pub puzzles_won: i64,
+    pub puzzles_lost: i64,
+    pub average_attempts: Option<f64>,
+    pub average_attempts_fraction: Option<f64>,
+
+    /// The longest current streak of the player among all sites.
+    pub current_streak: i64,
+
+    /// The name of the site with the longest current streak, if there is a current streak.
+    pub current_streak_site: Option<String>,
+}
+impl PlayerSummary {
+    /// Summarizes the statistics of a player within a period. `window_stats` are the statistics
+    /// of the period while `streak_stats` are those up to the end of the period, from which the
+    /// current streaks are taken. Returns `None` if the player has not played within the period.
+    pub fn new(window_stats: &[Stats], streak_stats: &[Stats]) -> Option<Self> {
+        let global = window_stats.iter()
+            .find(|s| s.subject == StatsSubject::Global)?;
+        if global.puzzles_won + global.puzzles_lost == 0 {
+            return None;
+        }
+
+        let mut current_streak = 0;
+        let mut current_streak_site = None;
+        for stats in streak_stats {
+            if let StatsSubject::Site { name, .. } = &stats.subject {
+                let site_streak = stats.current_streak.unwrap_or(0);
+                if site_streak > current_streak {
+                    current_streak = site_streak;
+                    current_streak_site = Some(name.clone());
+                }
+            }
+        }
+
+        Some(Self {
+            puzzles_won: global.puzzles_won,
+            puzzles_lost: global.puzzles_lost,
+            average_attempts: global.average_attempts,
+            average_attempts_fraction: global.average_attempts_fraction,
+            current_streak,
+            current_streak_site,
+        })
+    }
+
+    /// Compares the results of two players: more victories rank first, followed by a lower
+    /// average number of attempts relative to the maximum and fewer defeats.
+    pub fn compare(&self, other: &Self) -> Ordering {
+        let fraction_key = |s: &Self| s.average_attempts_fraction.unwrap_or(f64::INFINITY);
+        other.puzzles_won.cmp(&self.puzzles_won)
+            .then_with(|| fraction_key(self).total_cmp(&fraction_key(other)))
+            .then_with(|| self.puzzles_lost.cmp(&other.puzzles_lost))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranks_of(attempts: &[(&str, Option<i64>)], max_attempts: Option<i64>) -> Vec<(usize, String)> {
+        let items = attempts.iter()
+            .map(|(name, a)| (name.to_string(), *a))
+            .collect();
+        rank_by(items, |a, b| compare_attempts(*a, *b, max_attempts))
+            .into_iter()
+            .map(|r| (r.rank, r.player_name))
+            .collect()
+    }
+
+    #[test]
+    fn test_fewer_attempts_rank_first() {
+        let ranks = ranks_of(&[("alice", Some(5)), ("bob", Some(3)), ("carol", Some(4))], Some(6));
+        assert_eq!(ranks, vec![(1, "bob".to_owned()), (2, "carol".to_owned()), (3, "alice".to_owned())]);
+    }
+
+    #[test]
+    fn test_ties_share_rank() {
+        let ranks = ranks_of(&[("carol", Some(4)), ("alice", Some(3)), ("bob", Some(3)), ("dave", Some(5))], Some(6));
+        assert_eq!(
+            ranks,
+            vec![(1, "alice".to_owned()), (1, "bob".to_owned()), (3, "carol".to_owned()), (4, "dave".to_owned())],
+        );
+    }
+
+    #[test]
+    fn test_defeats_rank_last() {
+        let ranks = ranks_of(&[("alice", None), ("bob", Some(7)), ("carol", Some(6))], Some(6));
+        assert_eq!(ranks, vec![(1, "carol".to_owned()), (2, "alice".to_owned()), (2, "bob".to_owned())]);
+    }
+
+    fn summary(won: i64, lost: i64, fraction: Option<f64>) -> PlayerSummary {
+        PlayerSummary {
+            puzzles_won: won,
+            puzzles_lost: lost,
+            average_attempts: None,
+            average_attempts_fraction: fraction,
+            current_streak: 0,
+            current_streak_site: None,
+        }
+    }
+
+    #[test]
+    fn test_summaries_ranked_by_wins_then_attempts() {
+        let items = vec![
+            ("alice".to_owned(), summary(3, 0, Some(0.6))),
+            ("bob".to_owned(), summary(4, 1, Some(0.7))),
+            ("carol".to_owned(), summary(3, 1, Some(0.5))),
+            ("dave".to_owned(), summary(0, 2, None)),
+        ];
+        let ranks: Vec<(usize, String)> = rank_by(items, PlayerSummary::compare)
+            .into_iter()
+            .map(|r| (r.rank, r.player_name))
+            .collect();
+        assert_eq!(
+            ranks,
+            vec![(1, "bob".to_owned()), (2, "carol".to_owned()), (3, "alice".to_owned()), (4, "dave".to_owned())],
+        );
+    }
+}
@@ -1,41 +1,58 @@
+mod api;
+mod auth;
+mod cli;
 mod config;
 mod database;
+mod export;
 mod filters;
+mod import;
+mod leaderboard;
 mod model;
+mod nyt;
+mod parsers;
+mod stats;
+mod verify;
 
 
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::convert::Infallible;
+use std::io;
 use std::path::PathBuf;
 use std::process::ExitCode;
 
 use askama::Template;
 use chrono::{Duration, Local, NaiveDate};
-use clap::Parser;
-use form_urlencoded;
-use http_body_util::{BodyExt, Full};
+use clap::{Parser, ValueEnum};
+use futures_util::StreamExt;
+use http_body_util::{BodyExt, Full, StreamBody};
+use http_body_util::combinators::UnsyncBoxBody;
 use hyper::{Method, Request, Response};
-use hyper::body::{Bytes, Incoming};
+use hyper::body::{Bytes, Frame, Incoming};
 use hyper::service::service_fn;
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use hyper_util::server::conn::auto::Builder;
-use once_cell::sync::Lazy;
 use percent_encoding::percent_decode_str;
 use rand::{Rng, thread_rng};
-use regex::Regex;
 use tokio::net::TcpListener;
 use tokio::sync::RwLock;
-use tracing::{error, info, warn};
+use tracing::{error, info};
 
-use crate::config::{CONFIG, CONFIG_PATH, load_config};
-use crate::database::{DbConnection, OptionResult};
-use crate::model::{Puzzle, PuzzleSite, SiteAndPuzzle, Stats, StatsSubject};
+use crate::auth::{find_access_token, get_request_token, session_cookie, session_removal_cookie};
+use crate::cli::Command;
+use crate::config::{AccessToken, CONFIG, CONFIG_PATH, load_config, TokenScope};
+use crate::database::{DbConnection, initialize_pool, OptionResult, StoreResult, UpdateResult};
+use crate::export::{ExportFormat, RecordEncoder, write_export};
+use crate::leaderboard::{compare_attempts, PlayerSummary, rank_by, Ranked};
+use crate::model::{Player, PuzzleSite, SiteAndPuzzle, Stats, StatsSubject};
+use crate::parsers::parse_puzzle_data;
+use crate::stats::{first_day_of_month, is_victory, load_stats, missing_dates, StatsWindow};
 
 
 #[derive(Parser)]
 struct Opts {
-    #[clap(short, long, default_value = "config.toml")] pub config_file: PathBuf,
+    #[clap(short, long, default_value = "config.toml", global = true)] pub config_file: PathBuf,
+    #[clap(subcommand)] pub command: Option<Command>,
 }
 
 
@@ -67,11 +84,13 @@ struct NoPuzzlesTemplate {
 #[derive(Clone, Debug, Eq, Hash, PartialEq, Template)]
 #[template(path = "puzzles.html")]
 struct PuzzlesTemplate {
+    pub player_name: Option<String>,
     pub allow_spoiling: bool,
     pub spoil: bool,
     pub puzzles: Vec<PuzzlePart>,
     pub date_opt: Option<NaiveDate>,
-    pub token: Option<String>,
+    pub can_edit: bool,
+    pub can_delete: bool,
     pub stats_upwards_curve: bool,
     pub static_prefix: String,
 }
@@ -98,13 +117,85 @@ struct SubPuzzle {
     pub victory: bool,
 }
 
+/// The fields describing a puzzle, as submitted via the populate and edit forms.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct PuzzleForm {
+    pub site: PuzzleSite,
+    pub result: String,
+    pub raw_solution: String,
+    pub date: NaiveDate,
+    /// The daily ordinal, or `None` if the next free one should be assigned.
+    pub day_ordinal: Option<i64>,
+    pub action: Option<String>,
+    pub overwrite: bool,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Template)]
 #[template(path = "populate.html")]
 struct PopulateTemplate {
+    pub player_name: Option<String>,
     pub sites: Vec<PuzzleSite>,
-    pub solved_sites: HashSet<i64>,
+    pub puzzle_counts: HashMap<i64, i64>,
+    pub selected_site_id: Option<i64>,
+    pub date_string: String,
     pub today_string: String,
-    pub token: Option<String>,
+    pub static_prefix: String,
+}
+impl PopulateTemplate {
+    pub fn is_selected_site(&self, site_id: &i64) -> bool {
+        self.selected_site_id == Some(*site_id)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Template)]
+#[template(path = "populate-preview.html")]
+struct PopulatePreviewTemplate {
+    pub site: PuzzleSite,
+    pub result: String,
+    pub solution: String,
+    pub date: NaiveDate,
+    pub day_ordinal: Option<i64>,
+    pub puzzle_opt: Option<PuzzlePart>,
+    pub won_attempts: Option<i64>,
+    pub error_opt: Option<String>,
+    pub spoil: bool,
+    pub static_prefix: String,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Template)]
+#[template(path = "puzzle-edit.html")]
+struct PuzzleEditTemplate {
+    pub puzzle_id: i64,
+    pub sites: Vec<PuzzleSite>,
+    pub site_id: i64,
+    pub result: String,
+    pub solution: String,
+    pub date_string: String,
+    pub today_string: String,
+    pub day_ordinal: Option<i64>,
+    pub error_opt: Option<String>,
+    pub static_prefix: String,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Template)]
+#[template(path = "puzzle-delete.html")]
+struct PuzzleDeleteTemplate {
+    pub puzzle: PuzzlePart,
+    pub date: NaiveDate,
+    pub spoil: bool,
+    pub static_prefix: String,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Template)]
+#[template(path = "populate-duplicate.html")]
+struct PopulateDuplicateTemplate {
+    pub site: PuzzleSite,
+    pub result: String,
+    pub solution: String,
+    pub date: NaiveDate,
+    pub day_ordinal: i64,
+    pub existing_puzzle_id: i64,
+    pub next_free_day_ordinal: i64,
     pub static_prefix: String,
 }
 
@@ -114,103 +205,71 @@ struct PopulateSuccessTemplate {
     pub static_prefix: String,
 }
 
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Template)]
+#[template(path = "missing.html")]
+struct MissingDaysTemplate {
+    pub player_name: Option<String>,
+    pub sites: Vec<SiteMissingDays>,
+    pub static_prefix: String,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct SiteMissingDays {
+    pub site: PuzzleSite,
+    /// The dates of the first and the latest recorded puzzle; `None` if no puzzle has been
+    /// recorded for this site.
+    pub recorded_range: Option<(NaiveDate, NaiveDate)>,
+    pub missing_dates: Vec<NaiveDate>,
+}
+
 #[derive(Clone, Debug, PartialEq, Template)]
 #[template(path = "stats.html")]
 struct StatsTemplate {
+    pub player_name: Option<String>,
     pub stats: Vec<Stats>,
+    pub window: StatsWindow,
+    pub from_date_string: String,
+    pub to_date_string: String,
+    pub months: Vec<NaiveDate>,
     pub static_prefix: String,
 }
 
+#[derive(Clone, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Template)]
+#[template(path = "login.html")]
+struct LoginTemplate {
+    pub logged_in_as: Option<String>,
+    pub error_opt: Option<String>,
+    pub static_prefix: String,
+}
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
-struct PuzzleData<'h, 'r, 't, 'p, 's> {
-    pub head: Cow<'h, str>,
-    pub raw_pattern: Cow<'r, str>,
-    pub tail: Cow<'t, str>,
-    pub pattern: Cow<'p, str>,
-    pub solution: Cow<'s, str>,
-    pub attempts: Option<usize>,
-    pub expected_solution_line_count: Option<usize>,
-}
-impl<'h, 'r, 't, 'p, 's> PuzzleData<'h, 'r, 't, 'p, 's> {
-    pub fn new<
-        H: Into<Cow<'h, str>>,
-        R: Into<Cow<'r, str>>,
-        T: Into<Cow<'t, str>>,
-        P: Into<Cow<'p, str>>,
-        S: Into<Cow<'s, str>>,
-    >(
-        head: H,
-        raw_pattern: R,
-        tail: T,
-        pattern: P,
-        solution: S,
-        attempts: Option<usize>,
-        expected_solution_line_count: Option<usize>,
-    ) -> Self {
-        Self {
-            head: head.into(),
-            raw_pattern: raw_pattern.into(),
-            tail: tail.into(),
-            pattern: pattern.into(),
-            solution: solution.into(),
-            attempts,
-            expected_solution_line_count,
-        }
-    }
+#[derive(Clone, Debug, PartialEq, Template)]
+#[template(path = "leaderboard.html")]
+struct LeaderboardTemplate {
+    pub date: NaiveDate,
+    pub allow_spoiling: bool,
+    pub spoil: bool,
+    pub puzzles: Vec<LeaderboardPuzzle>,
+    pub periods: Vec<LeaderboardPeriod>,
+    pub static_prefix: String,
 }
 
+/// The entries of all players for the same puzzle, i.e. the same site and daily ordinal on the
+/// same day.
+#[derive(Clone, Debug, PartialEq)]
+struct LeaderboardPuzzle {
+    pub site: PuzzleSite,
+    pub day_ordinal: i64,
+    /// The rank, player name, number of attempts (`None` if lost) and puzzle of each player.
+    pub entries: Vec<(usize, String, Option<i64>, PuzzlePart)>,
+}
 
-static RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
-    // squares: black, white, [red, blue, orange, yellow, green, purple, brown]
-    "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+",
-    "(?:",
-        "\r?\n",
-        "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+",
-    ")*",
-)).unwrap());
-
-static GEO_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
-    // squares as above
-    // arrows: right, [left, up, down], [up-left, up-right, down-left, down-right], party popper
-    // emoji variant selector after the arrow (optional)
-    "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+[\u{27A1}\u{2B05}-\u{2B07}\u{2196}-\u{2199}\u{1F389}]\u{FE0F}?",
-    "(?:",
-        "\r?\n",
-        "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+[\u{27A1}\u{2B05}-\u{2B07}\u{2196}-\u{2199}\u{1F389}]\u{FE0F}?",
-    ")*",
-)).unwrap());
-
-static AUDIO_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
-    // squares as above, but only one row
-    // emoji variant selector optionally after each square
-    "(?:[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]\u{FE0F}?)+",
-)).unwrap());
-
-static GLOBLE_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
-    // squares as above, but line breaks may be in between
-    "(?:[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]\\s*)+",
-)).unwrap());
-
-static WORDLE32_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
-    // on success: twice: digit (U+0030 to U+0039), emoji variant selector (U+FE0F), enclosing keycap (U+20E3)
-    // on failure: two red squares (U+1F7E5)
-    // above chunk four times, separated by spaces (U+0020)
-    // above chunk multiple (generally eight) times, separated by newlines (optionally U+000D, then U+000A)
-    "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
-    "(?:",
-        "[ ]",
-        "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
-    "){3}",
-    "(?:",
-        "\r?\n",
-        "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
-        "(?:",
-            "[ ]",
-            "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
-        "){3}",
-    ")*",
-)).unwrap());
+#[derive(Clone, Debug, PartialEq)]
+struct LeaderboardPeriod {
+    pub title: String,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub summaries: Vec<Ranked<PlayerSummary>>,
+}
 
 
 fn return_500() -> Result<Response<Full<Bytes>>, Infallible> {
@@ -245,11 +304,27 @@ fn return_404<P: Into<String>>(static_prefix: P) -> Result<Response<Full<Bytes>>
     render_template(&template, 404, HashMap::new())
 }
 
+fn return_405(allow: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    let body = Full::new(Bytes::from(format!("invalid method; requires {}", allow)));
+    let response_res = Response::builder()
+        .status(405)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .header("Allow", allow)
+        .body(body);
+    match response_res {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("failed to obtain 405 response: {}", e);
+            return_500()
+        },
+    }
+}
+
 fn to_path_segments<'a>(path: &'a str, strip_trailing_empty: bool) -> Option<Vec<Cow<'a, str>>> {
     let mut segments = Vec::new();
     let pieces_percent: Vec<&str> = path.split('/').collect();
     for (i, piece_percent) in pieces_percent.iter().enumerate() {
-        if piece_percent.len() == 0 {
+        if piece_percent.is_empty() {
             if i == 0 {
                 continue;
             }
@@ -274,7 +349,7 @@ fn to_path_segments<'a>(path: &'a str, strip_trailing_empty: bool) -> Option<Vec
     Some(segments)
 }
 
-fn get_query_pairs(uri: &hyper::Uri) -> HashMap<Cow<str>, Cow<str>> {
+fn get_query_pairs(uri: &hyper::Uri) -> HashMap<Cow<'_, str>, Cow<'_, str>> {
     if let Some(query_string) = uri.query() {
         form_urlencoded::parse(query_string.as_bytes())
             .collect()
@@ -283,21 +358,61 @@ fn get_query_pairs(uri: &hyper::Uri) -> HashMap<Cow<str>, Cow<str>> {
     }
 }
 
-async fn has_valid_token(query_pairs: &HashMap<Cow<'_, str>, Cow<'_, str>>, value_if_no_token_configured: bool) -> bool {
+/// Returns the name of the player to whom the pages outside of `player/{name}/` and the tokens
+/// without a player belong.
+async fn default_player_name() -> String {
+    let config_guard = CONFIG
+        .get().expect("CONFIG not set")
+        .read().await;
+    config_guard.default_player.clone()
+}
+
+/// Returns the name of the player for display in page headings, or `None` if it is the default
+/// player.
+async fn displayed_player_name(player: &Player) -> Option<String> {
+    if player.name == default_player_name().await {
+        None
+    } else {
+        Some(player.name.clone())
+    }
+}
+
+/// Returns whether the given token of a request is a token of any player with the given scope.
+async fn has_scope(token: Option<&AccessToken>, scope: TokenScope, value_if_no_token_configured: bool) -> bool {
     let config_guard = CONFIG
         .get().expect("CONFIG not set")
         .read().await;
-    if config_guard.write_tokens.len() == 0 {
+    if config_guard.tokens.is_empty() {
         return value_if_no_token_configured;
     }
 
-    if let Some(token) = query_pairs.get("token") {
-        if config_guard.write_tokens.iter().any(|t| t == token) {
-            return true;
-        }
+    token.map(|t| t.has_scope(scope)).unwrap_or(false)
+}
+
+/// Returns whether a request with the given token may perform the actions of the given scope on
+/// the puzzles of the given player, i.e. whether the token belongs to that player and has that
+/// scope or no tokens are configured at all.
+async fn has_player_scope(token: Option<&AccessToken>, scope: TokenScope, player_name: &str) -> bool {
+    let config_guard = CONFIG
+        .get().expect("CONFIG not set")
+        .read().await;
+    if config_guard.tokens.is_empty() {
+        return true;
     }
 
-    false
+    let default_player = config_guard.default_player.as_str();
+    token
+        .map(|t| t.has_scope(scope) && t.player(default_player) == player_name)
+        .unwrap_or(false)
+}
+
+/// Returns the name of the player to whom the given token belongs.
+async fn token_player_name(token: Option<&AccessToken>) -> Option<String> {
+    let token = token?;
+    let config_guard = CONFIG
+        .get().expect("CONFIG not set")
+        .read().await;
+    Some(token.player(&config_guard.default_player).to_owned())
 }
 
 fn render_template<T: Template>(
@@ -330,18 +445,27 @@ fn render_template<T: Template>(
     Ok(response)
 }
 
-fn return_internal_redirect(base_path_segs: &[Cow<str>], path: &str, code: u16) -> Result<Response<Full<Bytes>>, Infallible> {
+fn return_internal_redirect(
+    base_path_segs: &[Cow<str>],
+    path: &str,
+    code: u16,
+    headers: HashMap<Cow<str>, Cow<str>>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
     let mut local_url = String::new();
     for bps in base_path_segs {
         local_url.push('/');
-        local_url.push_str(&bps);
+        local_url.push_str(bps);
     }
     local_url.push_str(path);
 
-    let response_res = Response::builder()
+    let mut builder = Response::builder()
         .status(code)
         .header("Location", &local_url)
-        .header("Content-Type", "text/plain; charset=utf-8")
+        .header("Content-Type", "text/plain; charset=utf-8");
+    for (k, v) in headers {
+        builder = builder.header(k.as_ref(), v.as_ref());
+    }
+    let response_res = builder
         .body(Full::new(Bytes::from(format!("Redirecting to {}", local_url))));
     match response_res {
         Ok(r) => Ok(r),
@@ -357,14 +481,36 @@ fn return_redirect_todays_wordle(base_path_segs: &[Cow<str>]) -> Result<Response
     let mut today_path = String::new();
     today_path.push_str("/wordle/");
     today_path.push_str(&today);
-    return_internal_redirect(&base_path_segs, &today_path, 303)
+    return_internal_redirect(base_path_segs, &today_path, 303, HashMap::new())
+}
+
+fn return_redirect_todays_leaderboard(base_path_segs: &[Cow<str>]) -> Result<Response<Full<Bytes>>, Infallible> {
+    let today = Local::now().naive_local().date().format("%Y-%m-%d").to_string();
+    let mut today_path = String::new();
+    today_path.push_str("/leaderboard/");
+    today_path.push_str(&today);
+    return_internal_redirect(base_path_segs, &today_path, 303, HashMap::new())
+}
+
+/// The body of a response, which is either complete or, in case of exports, streamed.
+type ResponseBody = UnsyncBoxBody<Bytes, io::Error>;
+
+fn full_body(body: Full<Bytes>) -> ResponseBody {
+    body
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+/// Converts a response with a complete body into one with a [`ResponseBody`].
+fn boxed_response(response_res: Result<Response<Full<Bytes>>, Infallible>) -> Result<Response<ResponseBody>, Infallible> {
+    response_res.map(|response| response.map(full_body))
 }
 
-async fn handle_request(req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+async fn handle_request(req: Request<Incoming>) -> Result<Response<ResponseBody>, Infallible> {
     let path_segs_opt = to_path_segments(req.uri().path(), false);
     let mut path_segs: Vec<String> = match path_segs_opt {
         Some(p) => p.iter().map(|s| s.clone().into_owned()).collect(),
-        None => return return_404(""),
+        None => return boxed_response(return_404("")),
     };
 
     let base_path = {
@@ -378,20 +524,20 @@ async fn handle_request(req: Request<Incoming>) -> Result<Response<Full<Bytes>>,
         Some(bps) => bps,
         None => {
             error!("failed to convert config base_path into segments");
-            return return_500();
+            return boxed_response(return_500());
         },
     };
 
     if base_path_segs.len() > path_segs.len() {
         // path cannot be a subpath of base_path if base_path has more components
-        return return_404("");
+        return boxed_response(return_404(""));
     }
     // all() returns true if the iterator is empty, e.g. if base_path_segs is empty
     let base_path_is_prefix_of_path = base_path_segs.iter()
         .zip(path_segs.iter().take(base_path_segs.len()))
         .all(|(bp, p)| bp == p);
     if !base_path_is_prefix_of_path {
-        return return_404("");
+        return boxed_response(return_404(""));
     }
 
     // remove path prefix
@@ -399,38 +545,78 @@ async fn handle_request(req: Request<Incoming>) -> Result<Response<Full<Bytes>>,
 
     // calculate static path prefix
     let mut static_prefix = String::new();
-    if path_segs.len() > 0 {
+    if !path_segs.is_empty() {
         for _ in 0..path_segs.len()-1 {
             static_prefix.push_str("../");
         }
     }
     static_prefix.push_str("static");
 
-    if path_segs.len() == 0 || (path_segs.len() == 1 && path_segs[0] == "") {
+    // pages below player/{name}/ show the puzzles of that player, all others those of the default
+    // player
+    let mut player_base_path_segs = base_path_segs.clone();
+    let player_name = if path_segs.len() >= 2 && path_segs[0] == "player" {
+        let name = path_segs[1].clone();
+        player_base_path_segs.push(Cow::Borrowed("player"));
+        player_base_path_segs.push(Cow::Owned(name.clone()));
+        path_segs.drain(0..2);
+        if !path_segs.is_empty() && (path_segs[0] == "api" || path_segs[0] == "static" || path_segs[0] == "export" || path_segs[0] == "leaderboard" || path_segs[0] == "login" || path_segs[0] == "logout") {
+            return boxed_response(return_404(static_prefix));
+        }
+        name
+    } else {
+        default_player_name().await
+    };
+    let base_path_segs = player_base_path_segs;
+
+    let response_res = if path_segs.is_empty() || (path_segs.len() == 1 && path_segs[0].is_empty()) {
         // http://example.com/wordle-archive or http://example.com/wordle-archive/
         return_redirect_todays_wordle(&base_path_segs)
     } else if path_segs.len() == 1 && path_segs[0] == "wordle" {
         // http://example.com/wordle-archive/wordle
         return_redirect_todays_wordle(&base_path_segs)
     } else if path_segs.len() == 2 && path_segs[0] == "wordle" {
-        if path_segs[1].len() == 0 {
+        if path_segs[1].is_empty() {
             // http://example.com/wordle-archive/wordle/
             return_redirect_todays_wordle(&base_path_segs)
         } else {
             // http://example.com/wordle-archive/wordle/2022-06-16
-            handle_wordle(req, static_prefix, path_segs.get(1)).await
+            handle_wordle(req, static_prefix, &player_name, path_segs.get(1)).await
         }
     } else if path_segs.len() == 2 && path_segs[0] == "puzzle" {
-        handle_puzzle(req, static_prefix, &path_segs[1]).await
+        handle_puzzle(req, static_prefix, &player_name, &path_segs[1]).await
+    } else if path_segs.len() == 3 && path_segs[0] == "puzzle" && path_segs[2] == "edit" {
+        handle_puzzle_edit(req, &base_path_segs, static_prefix, &player_name, &path_segs[1]).await
+    } else if path_segs.len() == 3 && path_segs[0] == "puzzle" && path_segs[2] == "delete" {
+        handle_puzzle_delete(req, &base_path_segs, static_prefix, &player_name, &path_segs[1]).await
     } else if path_segs.len() == 1 && path_segs[0] == "populate" {
-        handle_populate(req, static_prefix).await
+        handle_populate(req, static_prefix, &player_name).await
+    } else if path_segs.len() == 1 && path_segs[0] == "export" {
+        return handle_export(req, static_prefix).await;
+    } else if path_segs.len() == 1 && path_segs[0] == "login" {
+        handle_login(req, &base_path_segs, static_prefix).await
+    } else if path_segs.len() == 1 && path_segs[0] == "logout" {
+        handle_logout(req, &base_path_segs).await
     } else if path_segs.len() == 1 && path_segs[0] == "stats" {
-        handle_stats(req, static_prefix).await
+        handle_stats(req, static_prefix, &player_name).await
+    } else if path_segs.len() == 1 && path_segs[0] == "missing" {
+        handle_missing(req, static_prefix, &player_name).await
+    } else if path_segs.len() == 1 && path_segs[0] == "leaderboard" {
+        return_redirect_todays_leaderboard(&base_path_segs)
+    } else if path_segs.len() == 2 && path_segs[0] == "leaderboard" {
+        if path_segs[1].is_empty() {
+            return_redirect_todays_leaderboard(&base_path_segs)
+        } else {
+            handle_leaderboard(req, static_prefix, &path_segs[1]).await
+        }
+    } else if path_segs.len() >= 2 && path_segs[0] == "api" {
+        api::handle_api(req, &path_segs[1..]).await
     } else if path_segs.len() == 2 && path_segs[0] == "static" {
         handle_static(req, static_prefix, &path_segs[1]).await
     } else {
         return_404(static_prefix)
-    }
+    };
+    boxed_response(response_res)
 }
 
 fn db_puzzle_to_puzzle_part(db_puzzle: &SiteAndPuzzle) -> PuzzlePart {
@@ -446,8 +632,12 @@ fn db_puzzle_to_puzzle_part(db_puzzle: &SiteAndPuzzle) -> PuzzlePart {
         let guess_lines = pattern_lines.iter().zip(solution_lines.iter())
             .map(|(p, s)| (p.to_owned(), s.to_owned()))
             .collect();
-        let solution = solution_lines.get(solution_lines.len() - sub_puzzle_patterns.len() + i)
-            .unwrap().clone();
+        // the solutions of the sub-puzzles are the last lines; fall back to an empty solution for
+        // puzzles stored with fewer guesses than sub-puzzles
+        let solution = (solution_lines.len() + i).checked_sub(sub_puzzle_patterns.len())
+            .and_then(|index| solution_lines.get(index))
+            .cloned()
+            .unwrap_or_default();
         let victory = pattern_lines.iter().any(|ln| ln.chars().all(|c| ![ 'M', 'W', '1', '2', '3', '4', '5' ].contains(&c)));
 
         sub_puzzles.push(SubPuzzle {
@@ -487,9 +677,29 @@ async fn check_allow_spoiling(puzzle_date: &NaiveDate) -> bool {
     }
 }
 
+/// Returns whether spoilers may be shown for puzzles on the given date (publicly or thanks to a
+/// valid token) and whether they should actually be shown (allowed and requested via `spoil`).
+async fn get_spoil_flags(query_pairs: &HashMap<Cow<'_, str>, Cow<'_, str>>, token: Option<&AccessToken>, puzzle_date: &NaiveDate) -> (bool, bool) {
+    let mut spoil = false;
+    if let Some(spoil_str) = query_pairs.get("spoil") {
+        if let Ok(spoil_bool) = spoil_str.parse() {
+            spoil = spoil_bool;
+        }
+    }
+
+    let allow_public_spoiling = check_allow_spoiling(puzzle_date).await;
+    let allow_private_spoiling = has_scope(token, TokenScope::SpoilerRead, false).await;
+    let allow_spoiling = allow_public_spoiling || allow_private_spoiling;
+    if !allow_spoiling {
+        spoil = false;
+    }
+    (allow_spoiling, spoil)
+}
+
 async fn handle_wordle<S: AsRef<str>, P: Into<String>>(
     req: Request<Incoming>,
     static_prefix: P,
+    player_name: &str,
     date_string_opt: Option<S>,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     let date_opt = match date_string_opt {
@@ -502,24 +712,24 @@ async fn handle_wordle<S: AsRef<str>, P: Into<String>>(
         None => None,
     };
 
-    let mut spoil = false;
     let query_pairs = get_query_pairs(req.uri());
-    if let Some(spoil_str) = query_pairs.get("spoil") {
-        if let Ok(spoil_bool) = spoil_str.parse() {
-            spoil = spoil_bool;
-        }
-    }
+    let token = get_request_token(req.headers(), &query_pairs).await;
 
-    let db_conn = match DbConnection::new().await {
+    let db_conn = match DbConnection::get().await {
         Some(c) => c,
         None => return return_500(), // error already logged
     };
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
 
     let date = match date_opt {
         Some(d) => d,
         None => {
             // get freshest date from database
-            match db_conn.get_most_recent_puzzle_date().await {
+            match db_conn.get_most_recent_puzzle_date(player.id).await {
                 OptionResult::Present(d) => d,
                 OptionResult::Absent => {
                     let template = NoPuzzlesTemplate {
@@ -532,15 +742,10 @@ async fn handle_wordle<S: AsRef<str>, P: Into<String>>(
         },
     };
 
-    let allow_public_spoiling = check_allow_spoiling(&date).await;
-    let allow_private_spoiling = has_valid_token(&query_pairs, false).await;
-    let allow_spoiling = allow_public_spoiling || allow_private_spoiling;
-    if !allow_spoiling {
-        spoil = false;
-    }
+    let (allow_spoiling, spoil) = get_spoil_flags(&query_pairs, token.as_ref(), &date).await;
 
     // obtain puzzles on that date
-    let db_puzzles = match db_conn.get_puzzles_on_date(date).await {
+    let db_puzzles = match db_conn.get_puzzles_on_date(player.id, date).await {
         Some(ps) => ps,
         None => return return_500(), // error already logged
     };
@@ -551,15 +756,18 @@ async fn handle_wordle<S: AsRef<str>, P: Into<String>>(
         puzzles.push(db_puzzle_to_puzzle_part(db_puzzle));
     }
 
-    let token = query_pairs.get("token").map(|t| t.clone().into_owned());
+    let can_edit = has_player_scope(token.as_ref(), TokenScope::Write, &player.name).await;
+    let can_delete = has_player_scope(token.as_ref(), TokenScope::Admin, &player.name).await;
     let stats_upwards_curve: bool = thread_rng().gen();
 
     let template = PuzzlesTemplate {
+        player_name: displayed_player_name(&player).await,
         allow_spoiling,
         spoil,
         puzzles,
         date_opt: Some(date),
-        token,
+        can_edit,
+        can_delete,
         stats_upwards_curve,
         static_prefix: static_prefix.into(),
     };
@@ -569,6 +777,7 @@ async fn handle_wordle<S: AsRef<str>, P: Into<String>>(
 async fn handle_puzzle<S: AsRef<str>, P: Into<String>>(
     req: Request<Incoming>,
     static_prefix: P,
+    player_name: &str,
     id_string: S,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
     let id: i64 = match id_string.as_ref().parse() {
@@ -576,525 +785,521 @@ async fn handle_puzzle<S: AsRef<str>, P: Into<String>>(
         Err(_) => return return_404(static_prefix),
     };
 
-    let mut spoil = false;
     let query_pairs = get_query_pairs(req.uri());
-    if let Some(spoil_str) = query_pairs.get("spoil") {
-        if let Ok(spoil_bool) = spoil_str.parse() {
-            spoil = spoil_bool;
-        }
-    }
+    let token = get_request_token(req.headers(), &query_pairs).await;
 
-    let db_conn = match DbConnection::new().await {
+    let db_conn = match DbConnection::get().await {
         Some(c) => c,
         None => return return_500(), // error already logged
     };
 
-    let db_puzzle = match db_conn.get_puzzle_by_id(id).await {
-        OptionResult::Present(d) => d,
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
         OptionResult::Absent => return return_404(static_prefix),
         OptionResult::Error => return return_500(), // error already logged
     };
+    let db_puzzle = match db_conn.get_puzzle_by_id(id).await {
+        OptionResult::Present(d) if d.puzzle.player_id == player.id => d,
+        OptionResult::Present(_)|OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
     let puzzle = db_puzzle_to_puzzle_part(&db_puzzle);
 
-    let allow_public_spoiling = check_allow_spoiling(&db_puzzle.puzzle.date).await;
-    let allow_private_spoiling = has_valid_token(&query_pairs, false).await;
-    let allow_spoiling = allow_public_spoiling || allow_private_spoiling;
-    if !allow_spoiling {
-        spoil = false;
-    }
+    let (allow_spoiling, spoil) = get_spoil_flags(&query_pairs, token.as_ref(), &db_puzzle.puzzle.date).await;
 
-    let token = query_pairs.get("token").map(|t| t.clone().into_owned());
+    let can_edit = has_player_scope(token.as_ref(), TokenScope::Write, &player.name).await;
+    let can_delete = has_player_scope(token.as_ref(), TokenScope::Admin, &player.name).await;
     let stats_upwards_curve: bool = thread_rng().gen();
 
     let template = PuzzlesTemplate {
+        player_name: displayed_player_name(&player).await,
         allow_spoiling,
         spoil,
         puzzles: vec![puzzle],
         date_opt: None,
-        token,
+        can_edit,
+        can_delete,
         stats_upwards_curve,
         static_prefix: static_prefix.into(),
     };
     render_template(&template, 200, HashMap::new())
 }
 
-async fn handle_populate<P: Into<String>>(req: Request<Incoming>, static_prefix: P) -> Result<Response<Full<Bytes>>, Infallible> {
+async fn handle_populate<P: Into<String>>(req: Request<Incoming>, static_prefix: P, player_name: &str) -> Result<Response<Full<Bytes>>, Infallible> {
     // check for token
     let query_pairs = get_query_pairs(req.uri());
-    if !has_valid_token(&query_pairs, true).await {
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    if !has_player_scope(token.as_ref(), TokenScope::Write, player_name).await {
         return return_403(static_prefix);
     }
 
     if req.method() == Method::POST {
-        handle_populate_post(req, static_prefix).await
+        handle_populate_post(req, static_prefix, player_name).await
     } else if req.method() == Method::GET {
-        handle_populate_get(&req, static_prefix, &query_pairs).await
+        handle_populate_get(&req, static_prefix, player_name, &query_pairs).await
     } else {
-        let body = Full::new(Bytes::from("invalid method; requires GET or POST"));
-        let response_res = Response::builder()
-            .status(405)
-            .header("Content-Type", "text/plain; charset=utf-8")
-            .header("Allow", "GET, POST")
-            .body(body);
-        match response_res {
-            Ok(r) => Ok(r),
-            Err(e) => {
-                error!("failed to obtain 405 response: {}", e);
-                return_500()
-            },
-        }
+        return_405("GET, POST")
+    }
+}
+
+/// Parses the date for which a puzzle is being populated, falling back to today if none is given.
+///
+/// Dates in the future are rejected.
+fn parse_populate_date(date_str_opt: Option<&str>) -> Result<NaiveDate, &'static str> {
+    let today = Local::now().naive_local().date();
+    let date = match date_str_opt {
+        Some(ds) if !ds.is_empty() => {
+            match NaiveDate::parse_from_str(ds, "%Y-%m-%d") {
+                Ok(d) => d,
+                Err(_) => return Err("invalid value for field \"date\""),
+            }
+        },
+        _ => today,
+    };
+    if date > today {
+        return Err("date is in the future");
     }
+    Ok(date)
 }
 
 async fn handle_populate_get<P: Into<String>>(
     _req: &Request<Incoming>,
     static_prefix: P,
+    player_name: &str,
     query_pairs: &HashMap<Cow<'_, str>, Cow<'_, str>>,
 ) -> Result<Response<Full<Bytes>>, Infallible> {
-    let db_conn = match DbConnection::new().await {
+    let db_conn = match DbConnection::get().await {
         Some(c) => c,
         None => return return_500(), // error already logged
     };
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
 
     let sites = match db_conn.get_sites().await {
         Some(ps) => ps,
         None => return return_500(), // error already logged
     };
     let today = Local::now().naive_local().date();
-    let solved_sites = match db_conn.get_solved_sites_for_date(today).await {
-        Some(ss) => ss,
-        None => return return_500(),
+    let date = match parse_populate_date(query_pairs.get("date").map(|d| d.as_ref())) {
+        Ok(d) => d,
+        Err(e) => return return_400(e, static_prefix),
+    };
+    let puzzle_counts = match db_conn.get_puzzle_counts_for_date(player.id, date).await {
+        Some(pc) => pc,
+        None => return return_500(), // error already logged
     };
+    let date_string = date.format("%Y-%m-%d").to_string();
     let today_string = today.format("%Y-%m-%d").to_string();
-    let token = query_pairs.get("token").map(|t| t.clone().into_owned());
+    let selected_site_id = query_pairs.get("site")
+        .and_then(|s| s.parse().ok());
 
     let template = PopulateTemplate {
+        player_name: displayed_player_name(&player).await,
         sites,
-        solved_sites,
+        puzzle_counts,
+        selected_site_id,
+        date_string,
         today_string,
-        token,
         static_prefix: static_prefix.into(),
     };
     render_template(&template, 200, HashMap::new())
 }
 
-fn decode_square(square: char, variant: &String) -> Option<char> {
-    match variant.as_str() {
-        "globle" => match square {
-            // white => wrong
-            '\u{2B1C}' => Some('W'),
-            // red => 1
-            '\u{1F7E5}' => Some('1'),
-            // orange => 2
-            '\u{1F7E7}' => Some('2'),
-            // yellow => 3
-            '\u{1F7E8}' => Some('3'),
-            // green => correct
-            '\u{1F7E9}' => Some('C'),
-            c => {
-                warn!("unexpected result character {:?}; ignoring", c);
-                None
-            },
-        },
-        "globlec" => match square {
-            // black => wrong
-            '\u{2B1B}' => Some('W'),
-            // orange => 1
-            '\u{1F7E7}' => Some('1'),
-            // yellow => 2
-            '\u{1F7E8}' => Some('2'),
-            // green => 3
-            '\u{1F7E9}' => Some('3'),
-            // blue => 4
-            '\u{1F7E6}' => Some('4'),
-            // purple => 5
-            '\u{1F7EA}' => Some('5'),
-            // red => correct
-            '\u{1F7E5}' => Some('C'),
-            c => {
-                warn!("unexpected result character {:?}; ignoring", c);
-                None
-            },
-        },
-        _ => match square {
-            // black, white => wrong
-            '\u{2B1B}'|'\u{2B1C}' => Some('W'),
-            // red, orange, yellow, purple, brown => misplaced
-            // (purple via Nerdle, orange and brown by assumption;
-            // red from Heardle to differentiate from wrong = skipped)
-            '\u{1F7E5}'|'\u{1F7E7}'|'\u{1F7E8}'|'\u{1F7EA}'|'\u{1F7EB}' => Some('M'),
-            // blue, green => correct
-            // (blue by assumption)
-            '\u{1F7E6}'|'\u{1F7E9}' => Some('C'),
-            c => {
-                warn!("unexpected result character {:?}; ignoring", c);
-                None
-            },
-        }
-    }
-}
-
-async fn handle_populate_post<P: Into<String>>(
-    req: Request<Incoming>,
-    static_prefix: P,
-) -> Result<Response<Full<Bytes>>, Infallible> {
-    let db_conn = match DbConnection::new().await {
-        Some(c) => c,
-        None => return return_500(), // error already logged
-    };
-
+/// Collects the URL-encoded form fields from the body of the request.
+async fn read_form_pairs(req: Request<Incoming>) -> Option<HashMap<String, String>> {
     let (_head, body) = req.into_parts();
     let body_bytes = match body.collect().await {
         Ok(bs) => bs.to_bytes().to_vec(),
         Err(e) => {
             error!("failed to assemble body bytes: {}", e);
-            return return_500();
+            return None;
         },
     };
 
     let mut form_pairs = HashMap::new();
     for (k, v) in form_urlencoded::parse(&body_bytes) {
-        form_pairs.insert(k, v);
+        form_pairs.insert(k.into_owned(), v.into_owned());
     }
+    Some(form_pairs)
+}
 
+/// Extracts and validates the fields describing a puzzle from a submitted form.
+///
+/// On failure, returns the reason to display to the user.
+fn parse_puzzle_form(form_pairs: &HashMap<String, String>, sites: &[PuzzleSite]) -> Result<PuzzleForm, String> {
     let site_id_str = match form_pairs.get("site") {
         Some(s) => s,
-        None => return return_400("missing field \"site\"", static_prefix),
+        None => return Err("missing field \"site\"".to_owned()),
     };
     let site_id: i64 = match site_id_str.parse() {
         Ok(s) => s,
-        Err(_) => return return_400("invalid value for field \"site\"", static_prefix),
+        Err(_) => return Err("invalid value for field \"site\"".to_owned()),
     };
 
     let day_ordinal_str = form_pairs.get("day-ordinal")
-        .map(|d| d.clone().into_owned())
-        .unwrap_or_else(|| "0".to_owned());
-    let day_ordinal: i64 = match day_ordinal_str.parse() {
-        Ok(d) => d,
-        Err(_) => return return_400("invalid value for field \"day-ordinal\"", static_prefix),
-    };
-
-    let sites = match db_conn.get_sites().await {
-        Some(ps) => ps,
-        None => return return_500(), // error already logged
+        .map(|d| d.trim())
+        .unwrap_or("0");
+    let day_ordinal = if day_ordinal_str.is_empty() || day_ordinal_str == "auto" {
+        None
+    } else {
+        match day_ordinal_str.parse() {
+            Ok(d) => Some(d),
+            Err(_) => return Err("invalid value for field \"day-ordinal\"".to_owned()),
+        }
     };
 
-    let site = match sites.iter().filter(|s| s.id == site_id).nth(0) {
-        Some(s) => s,
-        None => return return_400(format!("site {} not found", site_id), static_prefix),
+    let site = match sites.iter().find(|s| s.id == site_id) {
+        Some(s) => s.clone(),
+        None => return Err(format!("site {} not found", site_id)),
     };
 
     let result = match form_pairs.get("result") {
-        Some(s) => s.replace("\r", ""),
-        None => return return_400("missing field \"result\"", static_prefix),
+        Some(s) => s.replace('\r', ""),
+        None => return Err("missing field \"result\"".to_owned()),
     };
     let raw_solution = match form_pairs.get("solution") {
-        Some(s) => s.replace("\r", ""),
-        None => return return_400("missing field \"solution\"", static_prefix),
+        Some(s) => s.replace('\r', ""),
+        None => return Err("missing field \"solution\"".to_owned()),
     };
 
-    let puzzle_data = if site.variant == "geo" {
-        if let Some(m) = GEO_RESULT_BLOCK_RE.find(&result) {
-            let mut result_string = String::new();
-            for line in m.as_str().split("\n") {
-                if result_string.len() > 0 {
-                    result_string.push('\n');
-                }
-
-                for c in line.chars() {
-                    if let Some(sq) = decode_square(c, &site.variant) {
-                        result_string.push(sq);
-                    } else if c == '\u{FE0F}' {
-                        // emoji variant selector; we don't need to store it
-                    } else {
-                        // probably the arrow; add it verbatim
-                        result_string.push(c);
-                    }
-                }
-            }
+    let date = parse_populate_date(form_pairs.get("date").map(|d| d.as_str()))?;
+    let action = form_pairs.get("action").cloned();
+    let overwrite = form_pairs.get("overwrite")
+        .map(|o| o == "yes")
+        .unwrap_or(false);
+
+    Ok(PuzzleForm {
+        site,
+        result,
+        raw_solution,
+        date,
+        day_ordinal,
+        action,
+        overwrite,
+    })
+}
 
-            let result_lines: Vec<&str> = result_string.split("\n").collect();
-            let last_result_line = result_lines.last().unwrap();
-            let victory =
-                last_result_line.contains('C')
-                && !last_result_line.chars().any(|c| [ 'M', 'W', '1', '2', '3', '4', '5' ].contains(&c))
-            ;
-            let expected_line_count = if victory {
-                result_lines.len()
-            } else {
-                result_lines.len() + 1
-            };
-            let attempts = if victory {
-                Some(expected_line_count)
-            } else {
-                None
-            };
+async fn handle_populate_post<P: Into<String>>(
+    req: Request<Incoming>,
+    static_prefix: P,
+    player_name: &str,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
 
-            let solution_line_count = raw_solution.split("\n").count();
-            if expected_line_count != solution_line_count {
-                return return_400(
-                    format!(
-                        "{} result lines, {} => expected {} solution lines but obtained {}",
-                        result_lines.len(), if victory { "victory" } else { "defeat" },
-                        expected_line_count, solution_line_count,
-                    ),
-                    static_prefix,
-                );
-            }
+    let form_pairs = match read_form_pairs(req).await {
+        Some(fp) => fp,
+        None => return return_500(), // error already logged
+    };
 
-            PuzzleData::new(
-                &result[0..m.start()],
-                m.as_str(),
-                &result[m.end()..],
-                result_string,
-                raw_solution.trim(),
-                attempts,
-                Some(expected_line_count),
-            )
-        } else {
-            return return_400("failed to decode guesses", static_prefix);
-        }
-    } else if site.variant == "audio" || site.variant == "globle" || site.variant == "globlec" {
-        let solution_lines: Vec<&str> = raw_solution.split('\n').collect();
-        let regex = if site.variant == "audio" {
-            &AUDIO_RESULT_BLOCK_RE
-        } else {
-            &GLOBLE_RESULT_BLOCK_RE
-        };
-        if let Some(m) = regex.find(&result) {
-            let mut result_string = String::new();
-            for c in m.as_str().chars() {
-                if c == '\u{FE0F}' {
-                    // emoji variant selector; skip it
-                } else if let Some(sq) = decode_square(c, &site.variant) {
-                    result_string.push(sq);
-                    if sq == 'C' {
-                        // correct answer! stop here
-                        break;
-                    }
-                }
-            }
+    let sites = match db_conn.get_sites().await {
+        Some(ps) => ps,
+        None => return return_500(), // error already logged
+    };
 
-            let victory = result_string.chars().any(|c| c == 'C');
-            let expected_line_count = if victory {
-                result_string.chars().count()
-            } else {
-                result_string.chars().count() + 1
-            };
-            if expected_line_count != solution_lines.len() {
-                return return_400(
-                    format!(
-                        "{} guesses derived from result {:?}, {} solution lines; must be the same",
-                        expected_line_count, result_string, solution_lines.len(),
-                    ),
-                    static_prefix,
-                );
-            }
+    let form = match parse_puzzle_form(&form_pairs, &sites) {
+        Ok(f) => f,
+        Err(e) => return return_400(e, static_prefix),
+    };
 
-            // intersperse newline characters in the result string
-            let mut newline_result_string = String::with_capacity(result_string.len()*2);
-            for c in result_string.chars() {
-                if newline_result_string.len() > 0 {
-                    newline_result_string.push('\n');
-                }
-                newline_result_string.push(c);
-            }
+    if form.action.as_deref() == Some("preview") {
+        return handle_populate_preview(&form.site, &player, form.result, form.raw_solution, form.date, form.day_ordinal, static_prefix);
+    }
 
-            let attempts = if victory {
-                Some(newline_result_string.bytes().filter(|b| *b == b'\n').count() + 1)
-            } else {
-                None
-            };
+    let puzzle_data = match parse_puzzle_data(&form.site, &form.result, &form.raw_solution) {
+        Ok(pd) => pd,
+        Err(e) => return return_400(e.to_string(), static_prefix),
+    };
 
-            PuzzleData::new(
-                &result[0..m.start()],
-                m.as_str(),
-                &result[m.end()..],
-                newline_result_string,
-                raw_solution.as_str(),
-                attempts,
-                Some(expected_line_count),
-            )
-        } else {
-            return return_400("failed to decode guesses", static_prefix);
+    let day_ordinal = match form.day_ordinal {
+        Some(dord) => dord,
+        None => match db_conn.get_next_free_day_ordinal(form.site.id, player.id, form.date, None).await {
+            Some(dord) => dord,
+            None => return return_500(), // error already logged
+        },
+    };
+    let puzzle = puzzle_data.into_puzzle(form.site.id, player.id, form.date, day_ordinal);
+    if form.overwrite {
+        if db_conn.store_or_overwrite_puzzle(&puzzle).await.is_none() {
+            return return_500(); // error already logged
         }
-    } else if site.variant == "wordle32" {
-        let solution = raw_solution.trim();
-        let solution_lines: Vec<&str> = solution.split('\n').collect();
-        if let Some(m) = WORDLE32_RESULT_BLOCK_RE.find(&result) {
-            let mut result_string = String::new();
-            for line in m.as_str().split('\n') {
-                if result_string.len() > 0 {
-                    result_string.push('\n');
-                }
-
-                for c in line.chars() {
-                    if c >= '0' && c <= '9' {
-                        result_string.push(c);
-                    } else if c == '\u{1F7E5}' { // red square
-                        result_string.push('X');
-                    } else if c == ' ' || c == '\n' {
-                        result_string.push(c);
-                    } else if c == '\u{FE0F}' || c == '\u{20E3}' {
-                        // ignore emoji variant selectors and enclosing keycaps
-                    } else {
-                        return return_400(
-                            format!(
-                                "unknown result character {} (U+{:04X})",
-                                c, u32::from(c),
-                            ),
-                            static_prefix,
-                        );
-                    }
-                }
-            }
+    } else {
+        match db_conn.store_puzzle(&puzzle).await {
+            StoreResult::Stored(_) => {},
+            StoreResult::Duplicate => return handle_populate_duplicate(&db_conn, &player, form, day_ordinal, static_prefix).await,
+            StoreResult::Error => return return_500(), // error already logged
+        }
+    }
 
-            let defeat_count = result_string.chars().filter(|c| *c == 'X').count() / 2;
-            let attempts = if defeat_count == 0 {
-                Some(solution_lines.len())
-            } else {
-                None
-            };
+    let template = PopulateSuccessTemplate {
+        static_prefix: static_prefix.into(),
+    };
+    render_template(&template, 200, HashMap::new())
+}
 
-            PuzzleData::new(
-                &result[0..m.start()],
-                m.as_str(),
-                &result[m.end()..],
-                result_string,
-                solution,
-                attempts,
-                None,
-            )
-        } else {
-            return return_400("failed to decode guesses", static_prefix);
-        }
-    } else {
-        // verify solution
-        let solution = raw_solution.trim();
-        let solution_lines: Vec<&str> = solution.split('\n').collect();
-
-        let mut puzzles: Vec<PuzzleData> = Vec::new();
-        for m in RESULT_BLOCK_RE.find_iter(&result) {
-            let mut result_string = String::new();
-            for line in m.as_str().split('\n') {
-                if result_string.len() > 0 {
-                    result_string.push('\n');
-                }
+/// Informs the user that a puzzle has already been stored for the given site, date and daily
+/// ordinal, offering to store it under the next free daily ordinal or to overwrite the existing one.
+async fn handle_populate_duplicate<P: Into<String>>(
+    db_conn: &DbConnection,
+    player: &Player,
+    form: PuzzleForm,
+    day_ordinal: i64,
+    static_prefix: P,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let existing_puzzle_id = match db_conn.get_puzzle_id_for_day(form.site.id, player.id, form.date, day_ordinal).await {
+        OptionResult::Present(pid) => pid,
+        OptionResult::Absent => {
+            error!(
+                "puzzle for site {} and player {} on {} with daily ordinal {} is a duplicate but cannot be found",
+                form.site.id, player.id, form.date, day_ordinal,
+            );
+            return return_500();
+        },
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let next_free_day_ordinal = match db_conn.get_next_free_day_ordinal(form.site.id, player.id, form.date, None).await {
+        Some(dord) => dord,
+        None => return return_500(), // error already logged
+    };
 
-                for c in line.chars() {
-                    if let Some(sq) = decode_square(c, &site.variant) {
-                        result_string.push(sq);
-                    }
-                }
-            }
+    let template = PopulateDuplicateTemplate {
+        site: form.site,
+        result: form.result,
+        solution: form.raw_solution,
+        date: form.date,
+        day_ordinal,
+        existing_puzzle_id,
+        next_free_day_ordinal,
+        static_prefix: static_prefix.into(),
+    };
+    render_template(&template, 409, HashMap::new())
+}
 
-            // victory: is there any line that consists only of "C"s (correct answers)?
-            let victory_index_opt = result_string
-                .split('\n')
-                .position(|ln| ln.chars().all(|c| c == 'C'));
-            let expected_line_count = if let Some(victory_index) = victory_index_opt {
-                victory_index + 1
-            } else {
-                result_string.split('\n').count()
-                // +1 is added further down (adding number of lost puzzles)
+fn handle_populate_preview<P: Into<String>>(
+    site: &PuzzleSite,
+    player: &Player,
+    result: String,
+    raw_solution: String,
+    date: NaiveDate,
+    day_ordinal: Option<i64>,
+    static_prefix: P,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let (puzzle_opt, error_opt) = match parse_puzzle_data(site, &result, &raw_solution) {
+        Ok(puzzle_data) => {
+            // render it the same way as a stored puzzle
+            // (the daily ordinal is only assigned when recording)
+            let puzzle = puzzle_data.into_puzzle(site.id, player.id, date, day_ordinal.unwrap_or(0));
+            let site_and_puzzle = SiteAndPuzzle {
+                site: site.clone(),
+                puzzle,
             };
-            let attempts = victory_index_opt.map(|vi| vi + 1);
-
-            puzzles.push(PuzzleData::new(
-                &result[0..m.start()],
-                m.as_str(),
-                &result[m.end()..],
-                result_string,
-                solution,
-                attempts,
-                Some(expected_line_count),
-            ));
-        }
+            (Some(db_puzzle_to_puzzle_part(&site_and_puzzle)), None)
+        },
+        Err(e) => (None, Some(e.to_string())),
+    };
+    let status = if error_opt.is_some() { 400 } else { 200 };
+    let won_attempts = puzzle_opt.as_ref()
+        .and_then(|puzzle| puzzle.attempts)
+        .filter(|attempts| is_victory(Some(*attempts), site.max_attempts));
+
+    let template = PopulatePreviewTemplate {
+        site: site.clone(),
+        result,
+        solution: raw_solution,
+        date,
+        day_ordinal,
+        puzzle_opt,
+        won_attempts,
+        error_opt,
+        // the author is authenticated
+        spoil: true,
+        static_prefix: static_prefix.into(),
+    };
+    render_template(&template, status, HashMap::new())
+}
 
-        if puzzles.len() == 0 {
-            return return_400("failed to decode guesses", static_prefix);
-        }
+async fn handle_puzzle_edit<S: AsRef<str>, P: Into<String>>(
+    req: Request<Incoming>,
+    base_path_segs: &[Cow<'_, str>],
+    static_prefix: P,
+    player_name: &str,
+    id_string: S,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    if !has_player_scope(token.as_ref(), TokenScope::Write, player_name).await {
+        return return_403(static_prefix);
+    }
 
-        let max_expected_line_count = puzzles.iter()
-            .map(|p| p.expected_solution_line_count.unwrap())
-            .max().expect("no puzzles?!");
-        let expected_line_count = if puzzles.iter().all(|p| p.attempts.is_some()) {
-            // all puzzles won
-            // => expected line count is the maximum of each subpuzzle
-            max_expected_line_count
-        } else {
-            // some puzzles lost
-            // => expected line count is the maximum of each subpuzzle
-            // + 1 for each lost subpuzzle
-            let lost_count = puzzles.iter()
-                .filter(|p| p.attempts.is_none())
-                .count();
-            max_expected_line_count + lost_count
-        };
+    let id: i64 = match id_string.as_ref().parse() {
+        Ok(i) => i,
+        Err(_) => return return_404(static_prefix),
+    };
+    if req.method() != Method::GET && req.method() != Method::POST {
+        return return_405("GET, POST");
+    }
 
-        if expected_line_count != solution_lines.len() {
-            return return_400(
-                format!(
-                    "expected {}, obtained {} solution lines",
-                    expected_line_count, solution_lines.len(),
-                ),
-                static_prefix,
-            );
-        }
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let db_puzzle = match db_conn.get_puzzle_by_id(id).await {
+        OptionResult::Present(d) if d.puzzle.player_id == player.id => d,
+        OptionResult::Present(_)|OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(ps) => ps,
+        None => return return_500(), // error already logged
+    };
+    let today_string = Local::now().naive_local().date().format("%Y-%m-%d").to_string();
 
-        let first_puzzle = &puzzles[0];
-        let last_puzzle = puzzles.last().unwrap();
+    if req.method() == Method::GET {
+        let puzzle_part = db_puzzle_to_puzzle_part(&db_puzzle);
+        let result = match filters::puzzle_string(&puzzle_part) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("failed to reassemble result of puzzle {}: {}", id, e);
+                return return_500();
+            },
+        };
+        let template = PuzzleEditTemplate {
+            puzzle_id: id,
+            sites,
+            site_id: db_puzzle.site.id,
+            result,
+            solution: db_puzzle.puzzle.solution,
+            date_string: db_puzzle.puzzle.date.format("%Y-%m-%d").to_string(),
+            today_string,
+            day_ordinal: Some(db_puzzle.puzzle.day_ordinal),
+            error_opt: None,
+            static_prefix: static_prefix.into(),
+        };
+        return render_template(&template, 200, HashMap::new());
+    }
 
-        let raw_patterns: Vec<Cow<str>> = puzzles.iter().map(|p| p.raw_pattern.clone()).collect();
-        let raw_pattern = raw_patterns.join("\n\n");
+    let form_pairs = match read_form_pairs(req).await {
+        Some(fp) => fp,
+        None => return return_500(), // error already logged
+    };
+    let form = match parse_puzzle_form(&form_pairs, &sites) {
+        Ok(f) => f,
+        Err(e) => return return_400(e, static_prefix),
+    };
 
-        let patterns: Vec<Cow<str>> = puzzles.iter().map(|p| p.pattern.clone()).collect();
-        let pattern = patterns.join("\n\n");
+    let (error, status) = match parse_puzzle_data(&form.site, &form.result, &form.raw_solution) {
+        Ok(pd) => {
+            let day_ordinal = match form.day_ordinal {
+                Some(dord) => dord,
+                None => match db_conn.get_next_free_day_ordinal(form.site.id, player.id, form.date, Some(id)).await {
+                    Some(dord) => dord,
+                    None => return return_500(), // error already logged
+                },
+            };
+            let mut puzzle = pd.into_puzzle(form.site.id, player.id, form.date, day_ordinal);
+            puzzle.id = id;
 
-        let mut attempts = Some(0);
-        for puzzle in &puzzles {
-            if let Some(a) = puzzle.attempts {
-                attempts = Some(attempts.unwrap().max(a));
-            } else {
-                // one of the partial puzzles failed = whole puzzle failed
-                attempts = None;
-                break;
+            match db_conn.update_puzzle(&puzzle).await {
+                UpdateResult::Updated => {
+                    let puzzle_path = format!("/puzzle/{}", id);
+                    return return_internal_redirect(base_path_segs, &puzzle_path, 303, HashMap::new());
+                },
+                UpdateResult::Duplicate => (
+                    "another puzzle has already been stored for this site, date and daily ordinal".to_owned(),
+                    409,
+                ),
+                UpdateResult::NotFound => return return_404(static_prefix),
+                UpdateResult::Error => return return_500(), // error already logged
             }
-        }
+        },
+        Err(e) => (e.to_string(), 400),
+    };
+
+    // let the user fix it
+    let template = PuzzleEditTemplate {
+        puzzle_id: id,
+        sites,
+        site_id: form.site.id,
+        result: form.result,
+        solution: form.raw_solution,
+        date_string: form.date.format("%Y-%m-%d").to_string(),
+        today_string,
+        day_ordinal: form.day_ordinal,
+        error_opt: Some(error),
+        static_prefix: static_prefix.into(),
+    };
+    render_template(&template, status, HashMap::new())
+}
+
+async fn handle_puzzle_delete<S: AsRef<str>, P: Into<String>>(
+    req: Request<Incoming>,
+    base_path_segs: &[Cow<'_, str>],
+    static_prefix: P,
+    player_name: &str,
+    id_string: S,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    if !has_player_scope(token.as_ref(), TokenScope::Admin, player_name).await {
+        return return_403(static_prefix);
+    }
 
-        PuzzleData::new(
-            first_puzzle.head.clone(),
-            raw_pattern,
-            last_puzzle.tail.clone(),
-            pattern,
-            first_puzzle.solution.clone(),
-            attempts,
-            Some(expected_line_count),
-        )
+    let id: i64 = match id_string.as_ref().parse() {
+        Ok(i) => i,
+        Err(_) => return return_404(static_prefix),
     };
 
-    let attempts_i64 = puzzle_data.attempts
-        .map(|a| a.try_into().expect("failed to convert attempt count to i64"));
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let db_puzzle = match db_conn.get_puzzle_by_id(id).await {
+        OptionResult::Present(d) if d.puzzle.player_id == player.id => d,
+        OptionResult::Present(_)|OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
 
-    let puzzle = Puzzle {
-        id: -1,
-        site_id,
-        date: Local::now().naive_local().date(),
-        day_ordinal,
-        head: puzzle_data.head.into_owned(),
-        tail: puzzle_data.tail.into_owned(),
-        pattern: puzzle_data.pattern.into_owned(),
-        solution: puzzle_data.solution.into_owned(),
-        attempts: attempts_i64,
-        raw_pattern: Some(puzzle_data.raw_pattern.into_owned()),
-    };
-    if !db_conn.store_puzzle(&puzzle).await {
-        return_500()
-    } else {
-        let template = PopulateSuccessTemplate {
+    if req.method() == Method::GET {
+        // ask for confirmation
+        let template = PuzzleDeleteTemplate {
+            puzzle: db_puzzle_to_puzzle_part(&db_puzzle),
+            date: db_puzzle.puzzle.date,
+            // the user is authenticated
+            spoil: true,
             static_prefix: static_prefix.into(),
         };
         render_template(&template, 200, HashMap::new())
+    } else if req.method() == Method::POST {
+        match db_conn.delete_puzzle(id).await {
+            OptionResult::Present(()) => {},
+            OptionResult::Absent => return return_404(static_prefix),
+            OptionResult::Error => return return_500(), // error already logged
+        }
+
+        let date_path = format!("/wordle/{}", db_puzzle.puzzle.date.format("%Y-%m-%d"));
+        return_internal_redirect(base_path_segs, &date_path, 303, HashMap::new())
+    } else {
+        return_405("GET, POST")
     }
 }
 
@@ -1109,6 +1314,11 @@ async fn run() -> ExitCode {
         .with_writer(stdout_non_blocking)
         .init();
 
+    // hashing a token does not require a configuration
+    if let Some(Command::HashToken(args)) = &opts.command {
+        return cli::hash_token(args);
+    }
+
     // store config file path
     CONFIG_PATH.set(opts.config_file)
         .expect("CONFIG_PATH already set");
@@ -1119,16 +1329,45 @@ async fn run() -> ExitCode {
         None => return ExitCode::FAILURE,
     };
 
-    // remember listen address
-    let listen_addr = config.listen_addr.clone();
-
     // store initial config
     CONFIG.set(RwLock::new(config))
         .expect("CONFIG already set");
 
-    // connect to the database once (to perform any necessary migrations)
+    match opts.command.unwrap_or(Command::Serve) {
+        Command::Serve => serve().await,
+        Command::Migrate(args) => cli::migrate(&args).await,
+        Command::CheckConfig => cli::check_config().await,
+        Command::Populate(args) => cli::populate(&args).await,
+        Command::Export(args) => cli::export(&args).await,
+        Command::Import(args) => cli::import(&args).await,
+        Command::ImportNyt(args) => cli::import_nyt(&args).await,
+        Command::Verify(args) => cli::verify(&args).await,
+        Command::HashToken(args) => cli::hash_token(&args),
+    }
+}
+
+async fn serve() -> ExitCode {
+    // remember listen address
+    let listen_addr = {
+        let config_guard = CONFIG
+            .get().expect("CONFIG not set")
+            .read().await;
+        config_guard.listen_addr
+    };
+
+    // set up the connection pool
+    if !initialize_pool().await {
+        // error already output
+        return ExitCode::FAILURE;
+    }
+
+    // perform any necessary migrations
     {
-        if DbConnection::new().await.is_none() {
+        let db_conn = match DbConnection::get().await {
+            Some(c) => c,
+            None => return ExitCode::FAILURE, // error already output
+        };
+        if !db_conn.run_migrations().await {
             // error already output
             return ExitCode::FAILURE;
         }
@@ -1155,24 +1394,347 @@ async fn run() -> ExitCode {
     }
 }
 
-async fn handle_stats<P: Into<String>>(_req: Request<Incoming>, static_prefix: P) -> Result<Response<Full<Bytes>>, Infallible> {
-    let db_conn = match DbConnection::new().await {
+async fn handle_missing<P: Into<String>>(_req: Request<Incoming>, static_prefix: P, player_name: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return return_500(), // error already logged
+    };
+    let outcomes = match db_conn.get_puzzle_outcomes(&StatsWindow::default(), Some(player.id)).await {
+        Some(o) => o,
+        None => return return_500(), // error already logged
+    };
+
+    let mut site_to_dates: HashMap<i64, Vec<NaiveDate>> = HashMap::new();
+    for outcome in &outcomes {
+        site_to_dates.entry(outcome.site_id)
+            .or_default()
+            .push(outcome.date);
+    }
+
+    let sites_missing_days = sites.into_iter()
+        .filter(|site| site.available)
+        .map(|site| {
+            let dates = site_to_dates.remove(&site.id)
+                .unwrap_or_default();
+            let recorded_range = dates.first().zip(dates.last())
+                .map(|(first, last)| (*first, *last));
+            let missing_dates = missing_dates(&dates);
+            SiteMissingDays { site, recorded_range, missing_dates }
+        })
+        .collect();
+
+    let template = MissingDaysTemplate {
+        player_name: displayed_player_name(&player).await,
+        sites: sites_missing_days,
+        static_prefix: static_prefix.into(),
+    };
+    render_template(&template, 200, HashMap::new())
+}
+
+async fn handle_stats<P: Into<String>>(req: Request<Incoming>, static_prefix: P, player_name: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    let query_pairs = get_query_pairs(req.uri());
+    let today = Local::now().naive_local().date();
+    let window = match StatsWindow::from_query(&query_pairs, today) {
+        Ok(w) => w,
+        Err(e) => return return_400(e, static_prefix),
+    };
+
+    let db_conn = match DbConnection::get().await {
         Some(c) => c,
         None => return return_500(), // error already logged
     };
 
-    let stats = match db_conn.get_stats().await {
+    let player = match db_conn.get_player_by_name(player_name).await {
+        OptionResult::Present(p) => p,
+        OptionResult::Absent => return return_404(static_prefix),
+        OptionResult::Error => return return_500(), // error already logged
+    };
+    let stats = match load_stats(&db_conn, player.id, &window, today).await {
         Some(s) => s,
         None => return return_500(), // error already logged
     };
+    let months = match db_conn.get_puzzle_months(player.id).await {
+        Some(m) => m,
+        None => return return_500(), // error already logged
+    };
 
+    let format_date_opt = |date_opt: Option<NaiveDate>| date_opt
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
     let template = StatsTemplate {
+        player_name: displayed_player_name(&player).await,
         stats,
+        window,
+        from_date_string: format_date_opt(window.from),
+        to_date_string: format_date_opt(window.to),
+        months,
         static_prefix: static_prefix.into(),
     };
     render_template(&template, 200, HashMap::new())
 }
 
+async fn handle_leaderboard<P: Into<String>>(req: Request<Incoming>, static_prefix: P, date_string: &str) -> Result<Response<Full<Bytes>>, Infallible> {
+    let date = match NaiveDate::parse_from_str(date_string, "%Y-%m-%d") {
+        Ok(d) => d,
+        Err(_) => return return_404(static_prefix),
+    };
+
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    let (allow_spoiling, spoil) = get_spoil_flags(&query_pairs, token.as_ref(), &date).await;
+
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return return_500(), // error already logged
+    };
+    let players = match db_conn.get_players().await {
+        Some(p) => p,
+        None => return return_500(), // error already logged
+    };
+    let db_puzzles = match db_conn.get_puzzles_of_all_players_on_date(date).await {
+        Some(p) => p,
+        None => return return_500(), // error already logged
+    };
+    let player_id_to_name: HashMap<i64, &str> = players.iter()
+        .map(|p| (p.id, p.name.as_str()))
+        .collect();
+
+    // the puzzles are ordered by site and daily ordinal
+    let mut puzzle_groups: Vec<Vec<&SiteAndPuzzle>> = Vec::new();
+    for db_puzzle in &db_puzzles {
+        match puzzle_groups.last_mut() {
+            Some(group) if group[0].site.id == db_puzzle.site.id && group[0].puzzle.day_ordinal == db_puzzle.puzzle.day_ordinal => {
+                group.push(db_puzzle);
+            },
+            _ => {
+                puzzle_groups.push(vec![db_puzzle]);
+            },
+        }
+    }
+    let puzzles = puzzle_groups.into_iter()
+        .map(|group| {
+            let site = group[0].site.clone();
+            let day_ordinal = group[0].puzzle.day_ordinal;
+            let entries = group.into_iter()
+                .map(|db_puzzle| {
+                    let player_name = player_id_to_name.get(&db_puzzle.puzzle.player_id)
+                        .map(|n| (*n).to_owned())
+                        .unwrap_or_default();
+                    (player_name, db_puzzle_to_puzzle_part(db_puzzle))
+                })
+                .collect();
+            let max_attempts = site.max_attempts;
+            LeaderboardPuzzle {
+                site,
+                day_ordinal,
+                entries: rank_by(entries, |a, b| compare_attempts(a.attempts, b.attempts, max_attempts))
+                    .into_iter()
+                    .map(|r| {
+                        // puzzles solved in more than the maximum attempts are shown as lost
+                        let won_attempts = r.item.attempts
+                            .filter(|_| is_victory(r.item.attempts, max_attempts));
+                        (r.rank, r.player_name, won_attempts, r.item)
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    // current streaks are taken from all puzzles up to the date
+    let today = Local::now().naive_local().date();
+    let streak_window = StatsWindow { from: None, to: Some(date) };
+    let period_windows = [
+        ("Last 7 days", StatsWindow { from: Some(date - Duration::days(6)), to: Some(date) }),
+        ("This month", StatsWindow { from: Some(first_day_of_month(date)), to: Some(date) }),
+    ];
+    let mut player_streak_stats = Vec::with_capacity(players.len());
+    for player in &players {
+        let streak_stats = match load_stats(&db_conn, player.id, &streak_window, today).await {
+            Some(s) => s,
+            None => return return_500(), // error already logged
+        };
+        player_streak_stats.push((player, streak_stats));
+    }
+
+    let mut periods = Vec::with_capacity(period_windows.len());
+    for (title, window) in &period_windows {
+        let mut summaries = Vec::with_capacity(players.len());
+        for (player, streak_stats) in &player_streak_stats {
+            let window_stats = match load_stats(&db_conn, player.id, window, today).await {
+                Some(s) => s,
+                None => return return_500(), // error already logged
+            };
+            if let Some(summary) = PlayerSummary::new(&window_stats, streak_stats) {
+                summaries.push((player.name.clone(), summary));
+            }
+        }
+        periods.push(LeaderboardPeriod {
+            title: (*title).to_owned(),
+            from: window.from.unwrap(),
+            to: date,
+            summaries: rank_by(summaries, PlayerSummary::compare),
+        });
+    }
+
+    let template = LeaderboardTemplate {
+        date,
+        allow_spoiling,
+        spoil,
+        puzzles,
+        periods,
+        static_prefix: static_prefix.into(),
+    };
+    render_template(&template, 200, HashMap::new())
+}
+
+async fn handle_login<P: Into<String>>(
+    req: Request<Incoming>,
+    base_path_segs: &[Cow<'_, str>],
+    static_prefix: P,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.method() == Method::GET {
+        let query_pairs = get_query_pairs(req.uri());
+        let token = get_request_token(req.headers(), &query_pairs).await;
+        let template = LoginTemplate {
+            logged_in_as: token_player_name(token.as_ref()).await,
+            error_opt: None,
+            static_prefix: static_prefix.into(),
+        };
+        render_template(&template, 200, HashMap::new())
+    } else if req.method() == Method::POST {
+        let form_pairs = match read_form_pairs(req).await {
+            Some(fp) => fp,
+            None => return return_500(), // error already logged
+        };
+        let token = form_pairs.get("token")
+            .map(|t| t.trim())
+            .unwrap_or("");
+        let access_token = match find_access_token(token).await {
+            Some(at) => at,
+            None => {
+                let template = LoginTemplate {
+                    logged_in_as: None,
+                    error_opt: Some("This token is not valid.".to_owned()),
+                    static_prefix: static_prefix.into(),
+                };
+                return render_template(&template, 403, HashMap::new());
+            },
+        };
+
+        let mut headers = HashMap::new();
+        headers.insert(Cow::Borrowed("Set-Cookie"), Cow::Owned(session_cookie(&access_token).await));
+        return_internal_redirect(base_path_segs, "/", 303, headers)
+    } else {
+        return_405("GET, POST")
+    }
+}
+
+async fn handle_logout(req: Request<Incoming>, base_path_segs: &[Cow<'_, str>]) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.method() != Method::POST {
+        return return_405("POST");
+    }
+
+    let mut headers = HashMap::new();
+    headers.insert(Cow::Borrowed("Set-Cookie"), Cow::Owned(session_removal_cookie().await));
+    return_internal_redirect(base_path_segs, "/login", 303, headers)
+}
+
+async fn handle_export<P: Into<String>>(req: Request<Incoming>, static_prefix: P) -> Result<Response<ResponseBody>, Infallible> {
+    // the export contains all solutions; require a token even if none are configured
+    let query_pairs = get_query_pairs(req.uri());
+    let token = get_request_token(req.headers(), &query_pairs).await;
+    if !has_scope(token.as_ref(), TokenScope::Admin, false).await {
+        return boxed_response(return_403(static_prefix));
+    }
+
+    let format_name = query_pairs.get("format")
+        .map(|f| f.as_ref())
+        .unwrap_or("jsonl");
+    let format = match ExportFormat::from_str(format_name, false) {
+        Ok(f) => f,
+        Err(_) => return boxed_response(return_400(format!("unknown export format {:?}", format_name), static_prefix)),
+    };
+
+    let db_conn = match DbConnection::get().await {
+        Some(c) => c,
+        None => return boxed_response(return_500()), // error already logged
+    };
+    let sites = match db_conn.get_sites().await {
+        Some(s) => s,
+        None => return boxed_response(return_500()), // error already logged
+    };
+    let players = match db_conn.get_players().await {
+        Some(p) => p,
+        None => return boxed_response(return_500()), // error already logged
+    };
+
+    let body: ResponseBody = match RecordEncoder::new(format, &players) {
+        Some(mut encoder) => {
+            // send the puzzles as they are read
+            let puzzle_stream = match db_conn.stream_all_puzzles().await {
+                Some(ps) => ps,
+                None => return boxed_response(return_500()), // error already logged
+            };
+            let frame_stream = puzzle_stream.map(move |puzzle_opt| {
+                // keep the database connection until the export has been sent
+                let _db_conn = &db_conn;
+
+                // errors abort the response, as its status has already been sent
+                let puzzle = puzzle_opt
+                    .ok_or_else(|| io::Error::other("failed to read puzzle"))?; // error already logged
+                match encoder.encode(&puzzle) {
+                    Ok(data) => Ok(Frame::data(Bytes::from(data))),
+                    Err(e) => {
+                        error!("failed to export: {}", e);
+                        Err(io::Error::other(e.to_string()))
+                    },
+                }
+            });
+            StreamBody::new(frame_stream).boxed_unsync()
+        },
+        None => {
+            // SQLite databases are assembled in memory
+            let puzzles = match db_conn.get_all_puzzles().await {
+                Some(p) => p,
+                None => return boxed_response(return_500()), // error already logged
+            };
+            let mut body_bytes = Vec::new();
+            if let Err(e) = write_export(format, &sites, &players, &puzzles, &mut body_bytes) {
+                error!("failed to export: {}", e);
+                return boxed_response(return_500());
+            }
+            full_body(Full::new(Bytes::from(body_bytes)))
+        },
+    };
+
+    let file_name = format!(
+        "wordle-archive-{}.{}",
+        Local::now().naive_local().date().format("%Y-%m-%d"),
+        format.file_extension(),
+    );
+    let response_res = Response::builder()
+        .status(200)
+        .header("Content-Type", format.content_type())
+        .header("Content-Disposition", format!("attachment; filename=\"{}\"", file_name))
+        .body(body);
+    match response_res {
+        Ok(r) => Ok(r),
+        Err(e) => {
+            error!("failed to assemble export response: {}", e);
+            boxed_response(return_500())
+        },
+    }
+}
+
 async fn handle_static<P: Into<String>>(_req: Request<Incoming>, static_prefix: P, static_path: &str) -> Result<Response<Full<Bytes>>, Infallible> {
     macro_rules! typescript {
         ($basename:expr) => {
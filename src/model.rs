@@ -1,7 +1,10 @@
 use chrono::NaiveDate;
+use serde::Serialize;
 
+use crate::stats::Sparkline;
 
-#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
 pub(crate) struct PuzzleSite {
     pub id: i64,
     pub name: String,
@@ -10,12 +13,23 @@ pub(crate) struct PuzzleSite {
     pub variant: String,
     pub notes: String,
     pub available: bool,
+    pub max_attempts: Option<i64>,
+    /// The highest number of days between two puzzles that does not break a streak; `None` if
+    /// streaks are only broken by defeats.
+    pub streak_max_gap_days: Option<i64>,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+pub(crate) struct Player {
+    pub id: i64,
+    pub name: String,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub(crate) struct Puzzle {
     pub id: i64,
     pub site_id: i64,
+    pub player_id: i64,
     pub date: NaiveDate,
     pub day_ordinal: i64,
     pub head: String,
@@ -32,21 +46,31 @@ pub(crate) struct SiteAndPuzzle {
     pub puzzle: Puzzle,
 }
 
-#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum StatsSubject {
     Global,
     Variant(String),
     Site{ id: i64, name: String, css_class: String },
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub(crate) struct Stats {
     pub subject: StatsSubject,
     pub puzzles_won: i64,
     pub puzzles_lost: i64,
-    pub average_attempts: f64,
+    pub average_attempts: Option<f64>,
+    /// The average number of attempts relative to the maximum number of attempts of each site,
+    /// considering only sites where this maximum is known.
+    pub average_attempts_fraction: Option<f64>,
     pub longest_streak: i64,
     pub current_streak: Option<i64>,
+    /// If the current streak has not been extended today, the last date on which it can still be
+    /// extended.
+    pub current_streak_deadline: Option<NaiveDate>,
+    pub attempt_counts: Vec<AttemptCount>,
+    /// Only filled for sites.
+    pub monthly_average_attempts: Vec<MonthlyAverage>,
 }
 impl Stats {
     pub fn percent_won(&self) -> f64 {
@@ -56,4 +80,64 @@ impl Stats {
             (self.puzzles_won as f64) * 100.0 / ((self.puzzles_won + self.puzzles_lost) as f64)
         }
     }
+
+    /// Returns the number of puzzles won for each number of attempts from 1 to the highest number
+    /// of attempts, including those with a count of zero.
+    pub fn attempt_distribution(&self) -> Vec<AttemptCount> {
+        let max_attempts = self.attempt_counts.iter()
+            .map(|ac| ac.attempts)
+            .max()
+            .unwrap_or(0);
+        (1..=max_attempts)
+            .map(|attempts| AttemptCount {
+                attempts,
+                count: self.attempt_counts.iter()
+                    .find(|ac| ac.attempts == attempts)
+                    .map(|ac| ac.count)
+                    .unwrap_or(0),
+            })
+            .collect()
+    }
+
+    /// Returns the highest number of puzzles won with the same number of attempts or lost,
+    /// whichever is greater.
+    pub fn max_attempt_count(&self) -> i64 {
+        self.attempt_counts.iter()
+            .map(|ac| ac.count)
+            .chain(std::iter::once(self.puzzles_lost))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the chart of the monthly average attempts, if there are any.
+    pub fn monthly_sparkline(&self) -> Option<Sparkline> {
+        Sparkline::new(&self.monthly_average_attempts, 120.0, 24.0)
+    }
+
+    /// Returns the given count relative to [`max_attempt_count`](Self::max_attempt_count), between
+    /// 0.0 and 1.0; used to scale the distribution graph.
+    pub fn attempt_count_fraction(&self, count: &i64) -> f64 {
+        let max_count = self.max_attempt_count();
+        if max_count == 0 {
+            0.0
+        } else {
+            (*count as f64) / (max_count as f64)
+        }
+    }
+}
+
+/// The average number of attempts of the puzzles won in a specific month.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct MonthlyAverage {
+    /// The first day of the month.
+    pub month: NaiveDate,
+    /// `None` if no puzzles have been won that month.
+    pub average_attempts: Option<f64>,
+}
+
+/// The number of puzzles won with a specific number of attempts.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) struct AttemptCount {
+    pub attempts: i64,
+    pub count: i64,
 }
@@ -0,0 +1,416 @@
+//! Conversion of the state stored by the New York Times Wordle in the browser's localStorage
+//! (`nyt-wordle-moogle/<uid>`) into puzzle results.
+
+
+use std::fmt;
+
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+use crate::database::{DbConnection, StoreResult};
+use crate::model::{Player, PuzzleSite};
+use crate::parsers::parse_puzzle_data;
+
+
+/// The length of a NYT Wordle word.
+const WORD_LENGTH: usize = 5;
+
+/// The maximum number of guesses in NYT Wordle.
+const MAX_GUESSES: usize = 6;
+
+/// The URL prefix by which the NYT Wordle site is recognized if no site name is given.
+const NYT_WORDLE_URL: &str = "https://www.nytimes.com/games/wordle";
+
+
+/// One or more localStorage dumps.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(untagged)]
+pub(crate) enum NytDump {
+    Many(Vec<NytState>),
+    One(NytState),
+}
+impl NytDump {
+    pub fn into_games(self) -> Vec<NytGame> {
+        match self {
+            Self::Many(states) => states.into_iter().map(|s| s.game).collect(),
+            Self::One(state) => vec![state.game],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+pub(crate) struct NytState {
+    pub game: NytGame,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct NytGame {
+    pub day_offset: i64,
+    pub board_state: Vec<String>,
+    #[serde(default)] pub evaluations: Vec<Option<Vec<String>>>,
+    #[serde(default)] pub solution: Option<String>,
+    pub status: String,
+    #[serde(default)] pub hard_mode: bool,
+}
+
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum NytConversionError {
+    /// The game has not been finished yet.
+    Unfinished { status: String },
+    /// The game was lost and the dump does not contain the solution.
+    SolutionUnknown,
+    /// A guess has neither an evaluation nor can it be evaluated against the solution.
+    InvalidGuess { guess: String },
+    /// The evaluation of a guess contains an unexpected value.
+    UnknownEvaluation { evaluation: String },
+}
+impl fmt::Display for NytConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unfinished { status }
+                => write!(f, "game has not been finished (status {:?})", status),
+            Self::SolutionUnknown
+                => write!(f, "game was lost and the solution is unknown"),
+            Self::InvalidGuess { guess }
+                => write!(f, "cannot evaluate guess {:?}", guess),
+            Self::UnknownEvaluation { evaluation }
+                => write!(f, "unknown evaluation {:?}", evaluation),
+        }
+    }
+}
+impl std::error::Error for NytConversionError {
+}
+
+
+/// A game converted into the format in which it would have been shared.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct NytResult {
+    pub date: NaiveDate,
+    pub result: String,
+    pub solution: String,
+}
+
+
+/// Returns the date of the NYT Wordle with the given day offset.
+pub(crate) fn day_offset_to_date(day_offset: i64) -> NaiveDate {
+    // Wordle 0 was published on 2021-06-19
+    NaiveDate::from_ymd_opt(2021, 6, 19).unwrap() + Duration::days(day_offset)
+}
+
+/// Rates a guess against the solution the way Wordle does, returning `C` (correct), `M`
+/// (misplaced) or `W` (wrong) for each letter.
+fn evaluate_guess(guess: &[char], solution: &[char]) -> String {
+    let mut rating = vec!['W'; guess.len()];
+    let mut unmatched: Vec<char> = Vec::with_capacity(solution.len());
+
+    // correct letters first
+    for (i, (g, s)) in guess.iter().zip(solution.iter()).enumerate() {
+        if g == s {
+            rating[i] = 'C';
+        } else {
+            unmatched.push(*s);
+        }
+    }
+
+    // then misplaced letters, each solution letter only once
+    for (i, g) in guess.iter().enumerate() {
+        if rating[i] == 'C' {
+            continue;
+        }
+        if let Some(pos) = unmatched.iter().position(|u| u == g) {
+            rating[i] = 'M';
+            unmatched.swap_remove(pos);
+        }
+    }
+
+    rating.into_iter().collect()
+}
+
+fn decode_evaluation(evaluation: &str) -> Result<char, NytConversionError> {
+    match evaluation {
+        "correct" => Ok('C'),
+        "present" => Ok('M'),
+        "absent" => Ok('W'),
+        other => Err(NytConversionError::UnknownEvaluation { evaluation: other.to_owned() }),
+    }
+}
+
+fn encode_square(rating: char) -> char {
+    match rating {
+        'C' => '\u{1F7E9}',
+        'M' => '\u{1F7E8}',
+        _ => '\u{2B1C}',
+    }
+}
+
+/// Formats the puzzle number with a thousands separator, as done by the NYT share text.
+fn format_puzzle_number(day_offset: i64) -> String {
+    let digits = day_offset.to_string();
+    let mut ret = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            ret.push(',');
+        }
+        ret.push(c);
+    }
+    ret
+}
+
+/// Converts a finished game into the result that would have been shared and the list of guesses.
+pub(crate) fn game_to_result(game: &NytGame) -> Result<NytResult, NytConversionError> {
+    let victory = match game.status.as_str() {
+        "WIN" => true,
+        "FAIL" => false,
+        other => return Err(NytConversionError::Unfinished { status: other.to_owned() }),
+    };
+
+    let guesses: Vec<String> = game.board_state.iter()
+        .filter(|g| !g.is_empty())
+        .map(|g| g.to_uppercase())
+        .collect();
+
+    let solution_opt = match &game.solution {
+        Some(s) => Some(s.to_uppercase()),
+        // if the game was won, the last guess is the solution
+        None if victory => guesses.last().cloned(),
+        None => None,
+    };
+    if !victory && solution_opt.is_none() {
+        return Err(NytConversionError::SolutionUnknown);
+    }
+
+    let mut rows = Vec::with_capacity(guesses.len());
+    for (i, guess) in guesses.iter().enumerate() {
+        let evaluation_opt = game.evaluations.get(i)
+            .and_then(|e| e.as_ref());
+        let rating: String = if let Some(evaluation) = evaluation_opt {
+            evaluation.iter()
+                .map(|e| decode_evaluation(e))
+                .collect::<Result<String, NytConversionError>>()?
+        } else if let Some(solution) = &solution_opt {
+            let guess_chars: Vec<char> = guess.chars().collect();
+            let solution_chars: Vec<char> = solution.chars().collect();
+            if guess_chars.len() != WORD_LENGTH || solution_chars.len() != WORD_LENGTH {
+                return Err(NytConversionError::InvalidGuess { guess: guess.clone() });
+            }
+            evaluate_guess(&guess_chars, &solution_chars)
+        } else {
+            return Err(NytConversionError::InvalidGuess { guess: guess.clone() });
+        };
+        rows.push(rating.chars().map(encode_square).collect::<String>());
+    }
+
+    let attempts = if victory { guesses.len().to_string() } else { "X".to_owned() };
+    let result = format!(
+        "Wordle {} {}/{}{}\n\n{}",
+        format_puzzle_number(game.day_offset),
+        attempts,
+        MAX_GUESSES,
+        if game.hard_mode { "*" } else { "" },
+        rows.join("\n"),
+    );
+
+    let mut solution_lines = guesses;
+    if !victory {
+        // the parser expects the solution after the failed guesses
+        solution_lines.push(solution_opt.unwrap());
+    }
+
+    Ok(NytResult {
+        date: day_offset_to_date(game.day_offset),
+        result,
+        solution: solution_lines.join("\n"),
+    })
+}
+
+
+/// The outcome of importing a single game.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub(crate) enum NytImportOutcome {
+    /// The game has been stored as a new puzzle.
+    Imported { day_offset: i64, date: NaiveDate, puzzle_id: i64 },
+    /// A puzzle has already been stored for the date of the game.
+    Skipped { day_offset: i64, date: NaiveDate },
+    /// The game could not be converted or stored.
+    Failed { day_offset: i64, message: String },
+}
+
+
+/// Finds the NYT Wordle site, either by the given name or by its URL.
+pub(crate) fn find_nyt_site<'s>(sites: &'s [PuzzleSite], name_opt: Option<&str>) -> Option<&'s PuzzleSite> {
+    match name_opt {
+        Some(name) => sites.iter().find(|s| s.name == name),
+        None => sites.iter().find(|s| s.url.starts_with(NYT_WORDLE_URL)),
+    }
+}
+
+/// Converts the games and stores them as puzzles of the given site and player with daily ordinal
+/// 0. Games for which the player has already stored a puzzle are skipped.
+pub(crate) async fn import_games(db_conn: &DbConnection, site: &PuzzleSite, player: &Player, games: &[NytGame]) -> Vec<NytImportOutcome> {
+    let mut outcomes = Vec::with_capacity(games.len());
+    for game in games {
+        let nyt_result = match game_to_result(game) {
+            Ok(r) => r,
+            Err(e) => {
+                outcomes.push(NytImportOutcome::Failed { day_offset: game.day_offset, message: e.to_string() });
+                continue;
+            },
+        };
+        let puzzle_data = match parse_puzzle_data(site, &nyt_result.result, &nyt_result.solution) {
+            Ok(pd) => pd,
+            Err(e) => {
+                outcomes.push(NytImportOutcome::Failed { day_offset: game.day_offset, message: e.to_string() });
+                continue;
+            },
+        };
+        let puzzle = puzzle_data.into_puzzle(site.id, player.id, nyt_result.date, 0);
+        let outcome = match db_conn.store_puzzle(&puzzle).await {
+            StoreResult::Stored(puzzle_id) => NytImportOutcome::Imported {
+                day_offset: game.day_offset,
+                date: nyt_result.date,
+                puzzle_id,
+            },
+            StoreResult::Duplicate => NytImportOutcome::Skipped {
+                day_offset: game.day_offset,
+                date: nyt_result.date,
+            },
+            StoreResult::Error => NytImportOutcome::Failed {
+                // details have already been logged
+                day_offset: game.day_offset,
+                message: "failed to store puzzle".to_owned(),
+            },
+        };
+        outcomes.push(outcome);
+    }
+    outcomes
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::test_site;
+
+    fn squares(rating: &str) -> String {
+        rating.chars().map(encode_square).collect()
+    }
+
+    fn evaluate(guess: &str, solution: &str) -> String {
+        let guess_chars: Vec<char> = guess.chars().collect();
+        let solution_chars: Vec<char> = solution.chars().collect();
+        evaluate_guess(&guess_chars, &solution_chars)
+    }
+
+    fn game(board_state: &[&str], solution: Option<&str>, status: &str) -> NytGame {
+        NytGame {
+            day_offset: 1000,
+            board_state: board_state.iter().map(|g| g.to_string()).collect(),
+            evaluations: Vec::new(),
+            solution: solution.map(|s| s.to_owned()),
+            status: status.to_owned(),
+            hard_mode: false,
+        }
+    }
+
+    #[test]
+    fn test_day_offset_to_date() {
+        assert_eq!(day_offset_to_date(0), NaiveDate::from_ymd_opt(2021, 6, 19).unwrap());
+        assert_eq!(day_offset_to_date(1000), NaiveDate::from_ymd_opt(2024, 3, 15).unwrap());
+    }
+
+    #[test]
+    fn test_evaluate_guess() {
+        assert_eq!(evaluate("CRANE", "CRANE"), "CCCCC");
+        assert_eq!(evaluate("CRANE", "SLATE"), "WWCWC");
+        assert_eq!(evaluate("TRACE", "CRATE"), "MCCMC");
+    }
+
+    #[test]
+    fn test_evaluate_guess_duplicate_letters() {
+        // only one E in the solution: the first unmatched E is misplaced, the second one wrong
+        assert_eq!(evaluate("SPEED", "ABIDE"), "WWMWM");
+        // a correct letter uses up its occurrence before misplaced ones are considered
+        assert_eq!(evaluate("EERIE", "THEME"), "MWWWC");
+        // two Es in the solution, both misplaced
+        assert_eq!(evaluate("EXEXX", "AEBEC"), "MWMWW");
+    }
+
+    #[test]
+    fn test_format_puzzle_number() {
+        assert_eq!(format_puzzle_number(0), "0");
+        assert_eq!(format_puzzle_number(999), "999");
+        assert_eq!(format_puzzle_number(1000), "1,000");
+        assert_eq!(format_puzzle_number(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn test_won_game() {
+        let result = game_to_result(&game(&["crane", "slate", "", "", "", ""], None, "WIN")).unwrap();
+        assert_eq!(result.date, NaiveDate::from_ymd_opt(2024, 3, 15).unwrap());
+        assert_eq!(result.result, format!("Wordle 1,000 2/6\n\n{}\n{}", squares("WWCWC"), squares("CCCCC")));
+        assert_eq!(result.solution, "CRANE\nSLATE");
+
+        let site = test_site("wordle", "wordle");
+        let data = parse_puzzle_data(&site, &result.result, &result.solution).unwrap();
+        assert_eq!(data.attempts, Some(2));
+    }
+
+    #[test]
+    fn test_evaluations_and_hard_mode() {
+        let mut hard_game = game(&["slate"], None, "WIN");
+        hard_game.hard_mode = true;
+        hard_game.evaluations = vec![
+            Some(vec!["correct".to_owned(); 5]),
+            None,
+        ];
+        let result = game_to_result(&hard_game).unwrap();
+        assert_eq!(result.result, format!("Wordle 1,000 1/6*\n\n{}", squares("CCCCC")));
+
+        hard_game.evaluations = vec![Some(vec!["bogus".to_owned(); 5])];
+        assert_eq!(
+            game_to_result(&hard_game),
+            Err(NytConversionError::UnknownEvaluation { evaluation: "bogus".to_owned() }),
+        );
+    }
+
+    #[test]
+    fn test_lost_game_with_solution() {
+        let guesses = ["crane", "crate", "grate", "irate", "orate", "prate"];
+        let result = game_to_result(&game(&guesses, Some("skate"), "FAIL")).unwrap();
+        let rows: Vec<String> = ["WWCWC", "WWCCC", "WWCCC", "WWCCC", "WWCCC", "WWCCC"].iter()
+            .map(|r| squares(r))
+            .collect();
+        assert_eq!(result.result, format!("Wordle 1,000 X/6\n\n{}", rows.join("\n")));
+        assert_eq!(result.solution, "CRANE\nCRATE\nGRATE\nIRATE\nORATE\nPRATE\nSKATE");
+
+        let site = test_site("wordle", "wordle");
+        let data = parse_puzzle_data(&site, &result.result, &result.solution).unwrap();
+        assert_eq!(data.attempts, None);
+    }
+
+    #[test]
+    fn test_lost_game_without_solution() {
+        let guesses = ["crane", "crate", "grate", "irate", "orate", "prate"];
+        assert_eq!(game_to_result(&game(&guesses, None, "FAIL")), Err(NytConversionError::SolutionUnknown));
+    }
+
+    #[test]
+    fn test_unfinished_game() {
+        assert_eq!(
+            game_to_result(&game(&["crane", "", "", "", "", ""], Some("skate"), "IN_PROGRESS")),
+            Err(NytConversionError::Unfinished { status: "IN_PROGRESS".to_owned() }),
+        );
+    }
+
+    #[test]
+    fn test_invalid_guess() {
+        assert_eq!(
+            game_to_result(&game(&["cranes", "skate"], None, "WIN")),
+            Err(NytConversionError::InvalidGuess { guess: "CRANES".to_owned() }),
+        );
+    }
+}
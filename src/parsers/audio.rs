@@ -0,0 +1,77 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::PuzzlePart;
+use crate::parsers::{
+    ParseError, parse_square_sequence, PuzzleData, reconstruct_square_sequence, ResultParser,
+};
+use crate::parsers::squares::decode_square;
+
+
+static AUDIO_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    // squares: black, white, [red, blue, orange, yellow, green, purple, brown], but only one row
+    // emoji variant selector optionally after each square
+    "(?:[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]\u{FE0F}?)+",
+).unwrap());
+
+
+fn encode_square(pattern_char: char) -> Option<char> {
+    match pattern_char {
+        'C' => Some('\u{1F7E9}'),
+        'M' => Some('\u{1F7E8}'),
+        'W' => Some('\u{2B1C}'),
+        _ => None,
+    }
+}
+
+
+/// Audio guessing games (Heardle and friends): a single row with one square per guess.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct AudioParser;
+impl ResultParser for AudioParser {
+    fn parse<'a>(&self, result: &'a str, raw_solution: &'a str) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+        parse_square_sequence(&AUDIO_RESULT_BLOCK_RE, decode_square, result, raw_solution)
+    }
+
+    fn reconstruct_guesses(&self, puzzle_part: &PuzzlePart) -> String {
+        reconstruct_square_sequence(puzzle_part, encode_square)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::to_puzzle_part;
+
+    #[test]
+    fn test_victory() {
+        let result = "#Heardle #100\n\n\u{1F50A}\u{2B1B}\u{FE0F}\u{1F7E5}\u{1F7E9}\u{2B1C}\u{FE0F}\u{2B1C}\u{FE0F}\u{2B1C}\u{FE0F}\n\n#Heardle";
+        let solution = "skipped\nwrong song\nright song";
+        let data = AudioParser.parse(result, solution).unwrap();
+        assert_eq!(data.head, "#Heardle #100\n\n\u{1F50A}");
+        assert_eq!(data.pattern, "W\nM\nC");
+        assert_eq!(data.attempts, Some(3));
+    }
+
+    #[test]
+    fn test_defeat() {
+        let result = "\u{1F7E5}\u{1F7E5}";
+        let data = AudioParser.parse(result, "a\nb\nc").unwrap();
+        assert_eq!(data.pattern, "M\nM");
+        assert_eq!(data.attempts, None);
+
+        assert_eq!(
+            AudioParser.parse(result, "a\nb"),
+            Err(ParseError::LineCountMismatch { expected: 3, actual: 2 }),
+        );
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let result = "\u{2B1C}\u{1F7E8}\u{1F7E9}";
+        let data = AudioParser.parse(result, "a\nb\nc").unwrap();
+        let puzzle_part = to_puzzle_part("audio", "heardle", data);
+        assert_eq!(AudioParser.reconstruct_guesses(&puzzle_part), result);
+    }
+}
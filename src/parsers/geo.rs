@@ -0,0 +1,155 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::PuzzlePart;
+use crate::parsers::{ParseError, PuzzleData, ResultParser};
+use crate::parsers::squares::decode_square;
+
+
+static GEO_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+    // squares: black, white, [red, blue, orange, yellow, green, purple, brown]
+    // arrows: right, [left, up, down], [up-left, up-right, down-left, down-right], party popper
+    // emoji variant selector after the arrow (optional)
+    "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+[\u{27A1}\u{2B05}-\u{2B07}\u{2196}-\u{2199}\u{1F389}]\u{FE0F}?",
+    "(?:",
+        "\r?\n",
+        "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+[\u{27A1}\u{2B05}-\u{2B07}\u{2196}-\u{2199}\u{1F389}]\u{FE0F}?",
+    ")*",
+)).unwrap());
+
+
+/// Geography guessing games (Worldle and friends): one row of squares per guess indicating
+/// proximity, followed by an arrow pointing towards the solution.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct GeoParser;
+impl ResultParser for GeoParser {
+    fn parse<'a>(&self, result: &'a str, raw_solution: &'a str) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+        let m = match GEO_RESULT_BLOCK_RE.find(result) {
+            Some(m) => m,
+            None => return Err(ParseError::GuessesNotDecoded),
+        };
+
+        let mut result_string = String::new();
+        for line in m.as_str().split('\n') {
+            if !result_string.is_empty() {
+                result_string.push('\n');
+            }
+
+            for c in line.chars() {
+                if c == '\u{FE0F}' {
+                    // emoji variant selector; we don't need to store it
+                } else if let Some(sq) = decode_square(c) {
+                    result_string.push(sq);
+                } else {
+                    // probably the arrow; add it verbatim
+                    result_string.push(c);
+                }
+            }
+        }
+
+        let result_lines: Vec<&str> = result_string.split('\n').collect();
+        let last_result_line = result_lines.last().unwrap();
+        let victory =
+            last_result_line.contains('C')
+            && !last_result_line.chars().any(|c| [ 'M', 'W', '1', '2', '3', '4', '5' ].contains(&c))
+        ;
+        let expected_line_count = if victory {
+            result_lines.len()
+        } else {
+            result_lines.len() + 1
+        };
+        let attempts = if victory {
+            Some(expected_line_count)
+        } else {
+            None
+        };
+
+        let solution_line_count = raw_solution.split('\n').count();
+        if expected_line_count != solution_line_count {
+            return Err(ParseError::LineCountMismatch {
+                expected: expected_line_count,
+                actual: solution_line_count,
+            });
+        }
+
+        Ok(PuzzleData::new(
+            &result[0..m.start()],
+            m.as_str(),
+            &result[m.end()..],
+            result_string,
+            raw_solution.trim(),
+            attempts,
+            Some(expected_line_count),
+        ))
+    }
+
+    fn reconstruct_guesses(&self, puzzle_part: &PuzzlePart) -> String {
+        let mut ret = String::new();
+        for (j, sub_puzzle) in puzzle_part.sub_puzzles.iter().enumerate() {
+            if j > 0 {
+                ret.push_str("\n\n");
+            }
+
+            for (i, (guess, _solution)) in sub_puzzle.guess_lines.iter().enumerate() {
+                for row_char in guess.chars() {
+                    if row_char == 'C' {
+                        ret.push('\u{1F7E9}');
+                    } else if row_char == 'M' {
+                        ret.push('\u{1F7E8}');
+                    } else if row_char == 'W' {
+                        ret.push('\u{2B1C}');
+                    } else {
+                        // probably the arrow behind the squares
+                        ret.push(row_char);
+                        // append the emoji variation selector
+                        ret.push('\u{FE0F}');
+                    }
+                }
+
+                if i < sub_puzzle.guess_lines.len()-1 {
+                    ret.push('\n');
+                }
+            }
+        }
+        ret
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::to_puzzle_part;
+
+    #[test]
+    fn test_victory() {
+        let result = "#Worldle #100 2/6 (100%)\n\u{1F7E9}\u{1F7E8}\u{2B1C}\u{2B1C}\u{2B1C}\u{2197}\u{FE0F}\n\u{1F7E9}\u{1F7E9}\u{1F7E9}\u{1F7E9}\u{1F7E9}\u{1F389}\nhttps://worldle.teuteuf.fr";
+        let solution = "France\nGermany";
+        let data = GeoParser.parse(result, solution).unwrap();
+        assert_eq!(data.head, "#Worldle #100 2/6 (100%)\n");
+        assert_eq!(data.tail, "\nhttps://worldle.teuteuf.fr");
+        assert_eq!(data.pattern, "CMWWW\u{2197}\nCCCCC\u{1F389}");
+        assert_eq!(data.attempts, Some(2));
+    }
+
+    #[test]
+    fn test_defeat() {
+        let result = "\u{1F7E9}\u{1F7E8}\u{2B1C}\u{2B1C}\u{2B1C}\u{2197}\u{FE0F}";
+        let solution = "France\nGermany";
+        let data = GeoParser.parse(result, solution).unwrap();
+        assert_eq!(data.attempts, None);
+
+        assert_eq!(
+            GeoParser.parse(result, "France"),
+            Err(ParseError::LineCountMismatch { expected: 2, actual: 1 }),
+        );
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let result = "\u{1F7E9}\u{1F7E8}\u{2B1C}\u{2197}\u{FE0F}\n\u{1F7E9}\u{1F7E9}\u{1F7E9}\u{1F389}\u{FE0F}";
+        let data = GeoParser.parse(result, "France\nGermany").unwrap();
+        let puzzle_part = to_puzzle_part("geo", "worldle", data);
+        assert_eq!(GeoParser.reconstruct_guesses(&puzzle_part), result);
+    }
+}
@@ -0,0 +1,147 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::warn;
+
+use crate::PuzzlePart;
+use crate::parsers::{
+    ParseError, parse_square_sequence, PuzzleData, reconstruct_square_sequence, ResultParser,
+};
+
+
+static GLOBLE_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(
+    // squares: black, white, [red, blue, orange, yellow, green, purple, brown]
+    // line breaks may be in between
+    "(?:[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]\\s*)+",
+).unwrap());
+
+
+fn decode_light_square(square: char) -> Option<char> {
+    match square {
+        // white => wrong
+        '\u{2B1C}' => Some('W'),
+        // red => 1
+        '\u{1F7E5}' => Some('1'),
+        // orange => 2
+        '\u{1F7E7}' => Some('2'),
+        // yellow => 3
+        '\u{1F7E8}' => Some('3'),
+        // green => correct
+        '\u{1F7E9}' => Some('C'),
+        c => {
+            warn!("unexpected result character {:?}; ignoring", c);
+            None
+        },
+    }
+}
+
+fn encode_light_square(pattern_char: char) -> Option<char> {
+    match pattern_char {
+        'W' => Some('\u{2B1C}'),
+        '1' => Some('\u{1F7E5}'),
+        '2' => Some('\u{1F7E7}'),
+        '3' => Some('\u{1F7E8}'),
+        'C' => Some('\u{1F7E9}'),
+        _ => None,
+    }
+}
+
+fn decode_classic_square(square: char) -> Option<char> {
+    match square {
+        // black => wrong
+        '\u{2B1B}' => Some('W'),
+        // orange => 1
+        '\u{1F7E7}' => Some('1'),
+        // yellow => 2
+        '\u{1F7E8}' => Some('2'),
+        // green => 3
+        '\u{1F7E9}' => Some('3'),
+        // blue => 4
+        '\u{1F7E6}' => Some('4'),
+        // purple => 5
+        '\u{1F7EA}' => Some('5'),
+        // red => correct
+        '\u{1F7E5}' => Some('C'),
+        c => {
+            warn!("unexpected result character {:?}; ignoring", c);
+            None
+        },
+    }
+}
+
+fn encode_classic_square(pattern_char: char) -> Option<char> {
+    match pattern_char {
+        'W' => Some('\u{2B1B}'),
+        '1' => Some('\u{1F7E7}'),
+        '2' => Some('\u{1F7E8}'),
+        '3' => Some('\u{1F7E9}'),
+        '4' => Some('\u{1F7E6}'),
+        '5' => Some('\u{1F7EA}'),
+        'C' => Some('\u{1F7E5}'),
+        _ => None,
+    }
+}
+
+
+/// Globle-style games: one square per guess, colored by proximity to the solution.
+///
+/// The "globle" variant uses the light color scheme, the "globlec" variant uses the classic
+/// (Globle: Capitals) color scheme.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GlobleParser {
+    decode_square: fn(char) -> Option<char>,
+    encode_square: fn(char) -> Option<char>,
+}
+impl GlobleParser {
+    pub const LIGHT: Self = Self {
+        decode_square: decode_light_square,
+        encode_square: encode_light_square,
+    };
+    pub const CLASSIC: Self = Self {
+        decode_square: decode_classic_square,
+        encode_square: encode_classic_square,
+    };
+}
+impl ResultParser for GlobleParser {
+    fn parse<'a>(&self, result: &'a str, raw_solution: &'a str) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+        parse_square_sequence(&GLOBLE_RESULT_BLOCK_RE, self.decode_square, result, raw_solution)
+    }
+
+    fn reconstruct_guesses(&self, puzzle_part: &PuzzlePart) -> String {
+        reconstruct_square_sequence(puzzle_part, self.encode_square)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::to_puzzle_part;
+
+    #[test]
+    fn test_light() {
+        let result = "\u{1F30E} Jun 16, 2022 \u{1F30D}\n\u{1F525} 1 | Avg. Guesses: 4\n\u{2B1C}\u{1F7E5}\u{1F7E7}\n\u{1F7E8}\u{1F7E9} = 5\n\n#globle";
+        let solution = "Chile\nChina\nIndia\nNepal\nBhutan";
+        let data = GlobleParser::LIGHT.parse(result, solution).unwrap();
+        assert_eq!(data.pattern, "W\n1\n2\n3\nC");
+        assert_eq!(data.attempts, Some(5));
+    }
+
+    #[test]
+    fn test_classic() {
+        let result = "\u{2B1B}\u{1F7E6}\u{1F7E5}";
+        let data = GlobleParser::CLASSIC.parse(result, "a\nb\nc").unwrap();
+        assert_eq!(data.pattern, "W\n4\nC");
+        assert_eq!(data.attempts, Some(3));
+
+        let puzzle_part = to_puzzle_part("globlec", "globlec", data);
+        assert_eq!(GlobleParser::CLASSIC.reconstruct_guesses(&puzzle_part), result);
+    }
+
+    #[test]
+    fn test_line_count_mismatch() {
+        assert_eq!(
+            GlobleParser::LIGHT.parse("\u{2B1C}\u{1F7E9}", "a"),
+            Err(ParseError::LineCountMismatch { expected: 2, actual: 1 }),
+        );
+    }
+}
@@ -0,0 +1,253 @@
+mod audio;
+mod geo;
+mod globle;
+mod squares;
+mod wordle32;
+
+
+use std::borrow::Cow;
+use std::fmt;
+
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::PuzzlePart;
+use crate::model::{Puzzle, PuzzleSite};
+
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct PuzzleData<'h, 'r, 't, 'p, 's> {
+    pub head: Cow<'h, str>,
+    pub raw_pattern: Cow<'r, str>,
+    pub tail: Cow<'t, str>,
+    pub pattern: Cow<'p, str>,
+    pub solution: Cow<'s, str>,
+    pub attempts: Option<usize>,
+    pub expected_solution_line_count: Option<usize>,
+}
+impl<'h, 'r, 't, 'p, 's> PuzzleData<'h, 'r, 't, 'p, 's> {
+    pub fn new<
+        H: Into<Cow<'h, str>>,
+        R: Into<Cow<'r, str>>,
+        T: Into<Cow<'t, str>>,
+        P: Into<Cow<'p, str>>,
+        S: Into<Cow<'s, str>>,
+    >(
+        head: H,
+        raw_pattern: R,
+        tail: T,
+        pattern: P,
+        solution: S,
+        attempts: Option<usize>,
+        expected_solution_line_count: Option<usize>,
+    ) -> Self {
+        Self {
+            head: head.into(),
+            raw_pattern: raw_pattern.into(),
+            tail: tail.into(),
+            pattern: pattern.into(),
+            solution: solution.into(),
+            attempts,
+            expected_solution_line_count,
+        }
+    }
+
+    pub fn into_puzzle(self, site_id: i64, player_id: i64, date: NaiveDate, day_ordinal: i64) -> Puzzle {
+        let attempts_i64 = self.attempts
+            .map(|a| a.try_into().expect("failed to convert attempt count to i64"));
+
+        Puzzle {
+            id: -1,
+            site_id,
+            player_id,
+            date,
+            day_ordinal,
+            head: self.head.into_owned(),
+            tail: self.tail.into_owned(),
+            pattern: self.pattern.into_owned(),
+            solution: self.solution.into_owned(),
+            attempts: attempts_i64,
+            raw_pattern: Some(self.raw_pattern.into_owned()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub(crate) enum ParseError {
+    GuessesNotDecoded,
+    LineCountMismatch { expected: usize, actual: usize },
+    UnknownResultCharacter { character: char },
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::GuessesNotDecoded
+                => write!(f, "failed to decode guesses"),
+            Self::LineCountMismatch { expected, actual }
+                => write!(f, "expected {} solution lines, obtained {}", expected, actual),
+            Self::UnknownResultCharacter { character }
+                => write!(f, "unknown result character {} (U+{:04X})", character, u32::from(*character)),
+        }
+    }
+}
+impl std::error::Error for ParseError {
+}
+
+
+/// Parses and reconstructs the results shared by a specific variant of puzzle.
+pub(crate) trait ResultParser : Sync {
+    /// Parses the result shared by the puzzle site along with the guesses (one per line) into
+    /// puzzle data to be stored.
+    fn parse<'a>(&self, result: &'a str, raw_solution: &'a str) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError>;
+
+    /// Reconstructs the guesses part of the shared result from the stored pattern.
+    ///
+    /// Used for puzzles that were stored without their raw pattern.
+    fn reconstruct_guesses(&self, puzzle_part: &PuzzlePart) -> String;
+}
+
+
+/// Returns the parser responsible for the given puzzle variant.
+pub(crate) fn parser_for_variant(variant: &str) -> &'static dyn ResultParser {
+    match variant {
+        "audio" => &audio::AudioParser,
+        "geo" => &geo::GeoParser,
+        "globle" => &globle::GlobleParser::LIGHT,
+        "globlec" => &globle::GlobleParser::CLASSIC,
+        "wordle32" => &wordle32::Wordle32Parser,
+        _ => &squares::SquaresParser,
+    }
+}
+
+/// Parses the result shared by the puzzle site along with the guesses using the parser for the
+/// site's variant.
+pub(crate) fn parse_puzzle_data<'a>(
+    site: &PuzzleSite,
+    result: &'a str,
+    raw_solution: &'a str,
+) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+    parser_for_variant(&site.variant)
+        .parse(result, raw_solution)
+}
+
+
+/// Parses a single row of squares, one square per guess, up to and including the first correct
+/// guess.
+///
+/// Shared by the variants where each guess is rated as a whole.
+fn parse_square_sequence<'a>(
+    block_regex: &Regex,
+    decode_square: fn(char) -> Option<char>,
+    result: &'a str,
+    raw_solution: &'a str,
+) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+    let solution_lines: Vec<&str> = raw_solution.split('\n').collect();
+    let m = match block_regex.find(result) {
+        Some(m) => m,
+        None => return Err(ParseError::GuessesNotDecoded),
+    };
+
+    let mut result_string = String::new();
+    for c in m.as_str().chars() {
+        if c == '\u{FE0F}' {
+            // emoji variant selector; skip it
+        } else if let Some(sq) = decode_square(c) {
+            result_string.push(sq);
+            if sq == 'C' {
+                // correct answer! stop here
+                break;
+            }
+        }
+    }
+
+    let victory = result_string.chars().any(|c| c == 'C');
+    let expected_line_count = if victory {
+        result_string.chars().count()
+    } else {
+        result_string.chars().count() + 1
+    };
+    if expected_line_count != solution_lines.len() {
+        return Err(ParseError::LineCountMismatch {
+            expected: expected_line_count,
+            actual: solution_lines.len(),
+        });
+    }
+
+    // intersperse newline characters in the result string
+    let mut newline_result_string = String::with_capacity(result_string.len()*2);
+    for c in result_string.chars() {
+        if !newline_result_string.is_empty() {
+            newline_result_string.push('\n');
+        }
+        newline_result_string.push(c);
+    }
+
+    let attempts = if victory {
+        Some(newline_result_string.bytes().filter(|b| *b == b'\n').count() + 1)
+    } else {
+        None
+    };
+
+    Ok(PuzzleData::new(
+        &result[0..m.start()],
+        m.as_str(),
+        &result[m.end()..],
+        newline_result_string,
+        raw_solution,
+        attempts,
+        Some(expected_line_count),
+    ))
+}
+
+/// Reconstructs a single row of squares, one square per guess.
+fn reconstruct_square_sequence(puzzle_part: &PuzzlePart, encode_square: fn(char) -> Option<char>) -> String {
+    let mut ret = String::new();
+    for (j, sub_puzzle) in puzzle_part.sub_puzzles.iter().enumerate() {
+        if j > 0 {
+            ret.push_str("\n\n");
+        }
+
+        for (guess, _solution) in &sub_puzzle.guess_lines {
+            for row_char in guess.chars() {
+                if let Some(square) = encode_square(row_char) {
+                    ret.push(square);
+                }
+            }
+        }
+    }
+    ret
+}
+
+
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use crate::{db_puzzle_to_puzzle_part, PuzzlePart};
+    use crate::model::{PuzzleSite, SiteAndPuzzle};
+    use crate::parsers::PuzzleData;
+
+    /// Returns a site of the given variant.
+    pub(crate) fn test_site(variant: &str, css_class: &str) -> PuzzleSite {
+        PuzzleSite {
+            id: 1,
+            name: "Test".to_owned(),
+            url: "https://example.com/".to_owned(),
+            css_class: css_class.to_owned(),
+            variant: variant.to_owned(),
+            notes: String::new(),
+            available: true,
+            max_attempts: None,
+            streak_max_gap_days: Some(1),
+        }
+    }
+
+    /// Converts freshly parsed puzzle data into the form used when displaying it.
+    pub(crate) fn to_puzzle_part(variant: &str, css_class: &str, puzzle_data: PuzzleData) -> PuzzlePart {
+        let site = test_site(variant, css_class);
+        let date = chrono::NaiveDate::from_ymd_opt(2022, 6, 16).unwrap();
+        let mut puzzle = puzzle_data.into_puzzle(site.id, 1, date, 0);
+        puzzle.raw_pattern = None;
+        db_puzzle_to_puzzle_part(&SiteAndPuzzle { site, puzzle })
+    }
+}
@@ -0,0 +1,240 @@
+use std::borrow::Cow;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tracing::warn;
+
+use crate::PuzzlePart;
+use crate::parsers::{ParseError, PuzzleData, ResultParser};
+
+
+static RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+    // squares: black, white, [red, blue, orange, yellow, green, purple, brown]
+    "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+",
+    "(?:",
+        "\r?\n",
+        "[\u{2B1B}\u{2B1C}\u{1F7E5}-\u{1F7EB}]+",
+    ")*",
+)).unwrap());
+
+
+pub(crate) fn decode_square(square: char) -> Option<char> {
+    match square {
+        // black, white => wrong
+        '\u{2B1B}'|'\u{2B1C}' => Some('W'),
+        // red, orange, yellow, purple, brown => misplaced
+        // (purple via Nerdle, orange and brown by assumption;
+        // red from Heardle to differentiate from wrong = skipped)
+        '\u{1F7E5}'|'\u{1F7E7}'|'\u{1F7E8}'|'\u{1F7EA}'|'\u{1F7EB}' => Some('M'),
+        // blue, green => correct
+        // (blue by assumption)
+        '\u{1F7E6}'|'\u{1F7E9}' => Some('C'),
+        c => {
+            warn!("unexpected result character {:?}; ignoring", c);
+            None
+        },
+    }
+}
+
+
+/// The classic Wordle-style result: one row of squares per guess, one square per letter, with
+/// multiple blocks of rows for multi-puzzle variants (Dordle, Quordle, ...).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct SquaresParser;
+impl ResultParser for SquaresParser {
+    fn parse<'a>(&self, result: &'a str, raw_solution: &'a str) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+        // verify solution
+        let solution = raw_solution.trim();
+        let solution_lines: Vec<&str> = solution.split('\n').collect();
+
+        let mut puzzles: Vec<PuzzleData> = Vec::new();
+        for m in RESULT_BLOCK_RE.find_iter(result) {
+            let mut result_string = String::new();
+            for line in m.as_str().split('\n') {
+                if !result_string.is_empty() {
+                    result_string.push('\n');
+                }
+
+                for c in line.chars() {
+                    if let Some(sq) = decode_square(c) {
+                        result_string.push(sq);
+                    }
+                }
+            }
+
+            // victory: is there any line that consists only of "C"s (correct answers)?
+            let victory_index_opt = result_string
+                .split('\n')
+                .position(|ln| ln.chars().all(|c| c == 'C'));
+            let expected_line_count = if let Some(victory_index) = victory_index_opt {
+                victory_index + 1
+            } else {
+                result_string.split('\n').count()
+                // +1 is added further down (adding number of lost puzzles)
+            };
+            let attempts = victory_index_opt.map(|vi| vi + 1);
+
+            puzzles.push(PuzzleData::new(
+                &result[0..m.start()],
+                m.as_str(),
+                &result[m.end()..],
+                result_string,
+                solution,
+                attempts,
+                Some(expected_line_count),
+            ));
+        }
+
+        if puzzles.is_empty() {
+            return Err(ParseError::GuessesNotDecoded);
+        }
+
+        let max_expected_line_count = puzzles.iter()
+            .map(|p| p.expected_solution_line_count.unwrap())
+            .max().expect("no puzzles?!");
+        let expected_line_count = if puzzles.iter().all(|p| p.attempts.is_some()) {
+            // all puzzles won
+            // => expected line count is the maximum of each subpuzzle
+            max_expected_line_count
+        } else {
+            // some puzzles lost
+            // => expected line count is the maximum of each subpuzzle
+            // + 1 for each lost subpuzzle
+            let lost_count = puzzles.iter()
+                .filter(|p| p.attempts.is_none())
+                .count();
+            max_expected_line_count + lost_count
+        };
+
+        if expected_line_count != solution_lines.len() {
+            return Err(ParseError::LineCountMismatch {
+                expected: expected_line_count,
+                actual: solution_lines.len(),
+            });
+        }
+
+        let first_puzzle = &puzzles[0];
+        let last_puzzle = puzzles.last().unwrap();
+
+        let raw_patterns: Vec<Cow<str>> = puzzles.iter().map(|p| p.raw_pattern.clone()).collect();
+        let raw_pattern = raw_patterns.join("\n\n");
+
+        let patterns: Vec<Cow<str>> = puzzles.iter().map(|p| p.pattern.clone()).collect();
+        let pattern = patterns.join("\n\n");
+
+        let mut attempts = Some(0);
+        for puzzle in &puzzles {
+            if let Some(a) = puzzle.attempts {
+                attempts = Some(attempts.unwrap().max(a));
+            } else {
+                // one of the partial puzzles failed = whole puzzle failed
+                attempts = None;
+                break;
+            }
+        }
+
+        Ok(PuzzleData::new(
+            first_puzzle.head.clone(),
+            raw_pattern,
+            last_puzzle.tail.clone(),
+            pattern,
+            first_puzzle.solution.clone(),
+            attempts,
+            Some(expected_line_count),
+        ))
+    }
+
+    fn reconstruct_guesses(&self, puzzle_part: &PuzzlePart) -> String {
+        let mut ret = String::new();
+
+        let correct_square = '\u{1F7E9}';
+        let misplaced_square = if puzzle_part.site.css_class == "nerdle" { '\u{1F7EA}' } else { '\u{1F7E8}' };
+        let wrong_square = '\u{2B1C}'; // or \u{2B1B} in dark mode
+        for (j, sub_puzzle) in puzzle_part.sub_puzzles.iter().enumerate() {
+            if j > 0 {
+                ret.push_str("\n\n");
+            }
+
+            for (i, (guess, _solution)) in sub_puzzle.guess_lines.iter().enumerate() {
+                for row_char in guess.chars() {
+                    if row_char == 'C' {
+                        ret.push(correct_square);
+                    } else if row_char == 'M' {
+                        ret.push(misplaced_square);
+                    } else if row_char == 'W' {
+                        ret.push(wrong_square);
+                    }
+                }
+
+                if i < sub_puzzle.guess_lines.len()-1 {
+                    ret.push('\n');
+                }
+            }
+        }
+
+        ret
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::to_puzzle_part;
+
+    #[test]
+    fn test_victory() {
+        let result = "Wordle 1,000 3/6\n\n\u{2B1B}\u{1F7E8}\u{2B1B}\u{2B1B}\u{2B1B}\n\u{2B1B}\u{1F7E9}\u{1F7E9}\u{2B1B}\u{1F7E8}\n\u{1F7E9}\u{1F7E9}\u{1F7E9}\u{1F7E9}\u{1F7E9}";
+        let solution = "CRANE\nSLOTH\nTHOSE\n";
+        let data = SquaresParser.parse(result, solution).unwrap();
+        assert_eq!(data.head, "Wordle 1,000 3/6\n\n");
+        assert_eq!(data.tail, "");
+        assert_eq!(data.pattern, "WMWWW\nWCCWM\nCCCCC");
+        assert_eq!(data.solution, "CRANE\nSLOTH\nTHOSE");
+        assert_eq!(data.attempts, Some(3));
+    }
+
+    #[test]
+    fn test_defeat() {
+        let result = "\u{2B1B}\u{2B1B}\n\u{1F7E8}\u{2B1B}\nend";
+        let solution = "AB\nCD\nEF";
+        let data = SquaresParser.parse(result, solution).unwrap();
+        assert_eq!(data.pattern, "WW\nMW");
+        assert_eq!(data.tail, "\nend");
+        assert_eq!(data.attempts, None);
+        assert_eq!(data.expected_solution_line_count, Some(3));
+    }
+
+    #[test]
+    fn test_multiple_sub_puzzles() {
+        let result = "Dordle\n\u{1F7E9}\u{1F7E9}\n\n\u{2B1B}\u{2B1B}\n\u{1F7E9}\u{1F7E9}";
+        let solution = "AB\nCD";
+        let data = SquaresParser.parse(result, solution).unwrap();
+        assert_eq!(data.pattern, "CC\n\nWW\nCC");
+        assert_eq!(data.raw_pattern, "\u{1F7E9}\u{1F7E9}\n\n\u{2B1B}\u{2B1B}\n\u{1F7E9}\u{1F7E9}");
+        assert_eq!(data.attempts, Some(2));
+    }
+
+    #[test]
+    fn test_line_count_mismatch() {
+        let result = "\u{1F7E9}\u{1F7E9}";
+        let solution = "AB\nCD";
+        assert_eq!(
+            SquaresParser.parse(result, solution),
+            Err(ParseError::LineCountMismatch { expected: 1, actual: 2 }),
+        );
+    }
+
+    #[test]
+    fn test_no_squares() {
+        assert_eq!(SquaresParser.parse("nothing here", "AB"), Err(ParseError::GuessesNotDecoded));
+    }
+
+    #[test]
+    fn test_reconstruct() {
+        let result = "\u{2B1C}\u{1F7E8}\n\u{1F7E9}\u{1F7E9}";
+        let data = SquaresParser.parse(result, "AB\nCD").unwrap();
+        let puzzle_part = to_puzzle_part("wordle", "wordle", data);
+        assert_eq!(SquaresParser.reconstruct_guesses(&puzzle_part), result);
+    }
+}
@@ -0,0 +1,150 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::PuzzlePart;
+use crate::parsers::{ParseError, PuzzleData, ResultParser};
+
+
+static WORDLE32_RESULT_BLOCK_RE: Lazy<Regex> = Lazy::new(|| Regex::new(concat!(
+    // on success: twice: digit (U+0030 to U+0039), emoji variant selector (U+FE0F), enclosing keycap (U+20E3)
+    // on failure: two red squares (U+1F7E5)
+    // above chunk four times, separated by spaces (U+0020)
+    // above chunk multiple (generally eight) times, separated by newlines (optionally U+000D, then U+000A)
+    "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
+    "(?:",
+        "[ ]",
+        "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
+    "){3}",
+    "(?:",
+        "\r?\n",
+        "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
+        "(?:",
+            "[ ]",
+            "(?:[0-9]\u{FE0F}\u{20E3}[0-9]\u{FE0F}\u{20E3}|\u{1F7E5}\u{1F7E5})",
+        "){3}",
+    ")*",
+)).unwrap());
+
+
+/// Duotrigordle: 32 simultaneous puzzles, each summarized by the number of the guess with which
+/// it was solved (or two red squares if it was not solved).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct Wordle32Parser;
+impl ResultParser for Wordle32Parser {
+    fn parse<'a>(&self, result: &'a str, raw_solution: &'a str) -> Result<PuzzleData<'a, 'a, 'a, 'a, 'a>, ParseError> {
+        let solution = raw_solution.trim();
+        let solution_lines: Vec<&str> = solution.split('\n').collect();
+        let m = match WORDLE32_RESULT_BLOCK_RE.find(result) {
+            Some(m) => m,
+            None => return Err(ParseError::GuessesNotDecoded),
+        };
+
+        let mut result_string = String::new();
+        for line in m.as_str().split('\n') {
+            if !result_string.is_empty() {
+                result_string.push('\n');
+            }
+
+            for c in line.chars() {
+                if c.is_ascii_digit() {
+                    result_string.push(c);
+                } else if c == '\u{1F7E5}' { // red square
+                    result_string.push('X');
+                } else if c == ' ' || c == '\n' {
+                    result_string.push(c);
+                } else if c == '\u{FE0F}' || c == '\u{20E3}' {
+                    // ignore emoji variant selectors and enclosing keycaps
+                } else {
+                    return Err(ParseError::UnknownResultCharacter {
+                        character: c,
+                    });
+                }
+            }
+        }
+
+        let defeat_count = result_string.chars().filter(|c| *c == 'X').count() / 2;
+        let attempts = if defeat_count == 0 {
+            Some(solution_lines.len())
+        } else {
+            None
+        };
+
+        Ok(PuzzleData::new(
+            &result[0..m.start()],
+            m.as_str(),
+            &result[m.end()..],
+            result_string,
+            solution,
+            attempts,
+            None,
+        ))
+    }
+
+    fn reconstruct_guesses(&self, puzzle_part: &PuzzlePart) -> String {
+        let mut ret = String::new();
+        for sub_puzzle in &puzzle_part.sub_puzzles {
+            for (i, pattern_line) in sub_puzzle.pattern_lines.iter().enumerate() {
+                if i > 0 {
+                    ret.push('\n');
+                }
+                for c in pattern_line.chars() {
+                    if c.is_ascii_digit() {
+                        ret.push(c);
+                        ret.push('\u{FE0F}');
+                        ret.push('\u{20E3}');
+                    } else if c == 'X' {
+                        ret.push('\u{1F7E5}');
+                    } else {
+                        ret.push(c);
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::test_utils::to_puzzle_part;
+
+    fn keycaps(digits: &str) -> String {
+        let mut ret = String::new();
+        for c in digits.chars() {
+            ret.push(c);
+            if c.is_ascii_digit() {
+                ret.push('\u{FE0F}');
+                ret.push('\u{20E3}');
+            }
+        }
+        ret
+    }
+
+    #[test]
+    fn test_victory() {
+        let result = format!("Daily Duotrigordle #1\nGuesses: 3/37\n{}\n{}\nduotrigordle.com", keycaps("01 02 03 03"), keycaps("02 02 01 03"));
+        let data = Wordle32Parser.parse(&result, "ABCDE\nFGHIJ\nKLMNO\n").unwrap();
+        assert_eq!(data.head, "Daily Duotrigordle #1\nGuesses: 3/37\n");
+        assert_eq!(data.pattern, "01 02 03 03\n02 02 01 03");
+        assert_eq!(data.tail, "\nduotrigordle.com");
+        assert_eq!(data.attempts, Some(3));
+    }
+
+    #[test]
+    fn test_defeat() {
+        let result = format!("{} \u{1F7E5}\u{1F7E5}", keycaps("01 02 03"));
+        let data = Wordle32Parser.parse(&result, "ABCDE\nFGHIJ\nKLMNO").unwrap();
+        assert_eq!(data.pattern, "01 02 03 XX");
+        assert_eq!(data.attempts, None);
+
+        let puzzle_part = to_puzzle_part("wordle32", "duotrigordle", data);
+        assert_eq!(Wordle32Parser.reconstruct_guesses(&puzzle_part), result);
+    }
+
+    #[test]
+    fn test_no_block() {
+        assert_eq!(Wordle32Parser.parse("01 02", "ABCDE"), Err(ParseError::GuessesNotDecoded));
+    }
+}
@@ -0,0 +1,861 @@
+//! Statistics computed from the outcomes of a selection of puzzles, e.g. those within a date range.
+
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+use crate::database::DbConnection;
+use crate::model::{AttemptCount, MonthlyAverage, PuzzleSite, Stats, StatsSubject};
+
+
+/// The outcome of a single puzzle, as required to compute statistics.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub(crate) struct PuzzleOutcome {
+    pub player_id: i64,
+    pub site_id: i64,
+    pub date: NaiveDate,
+    pub day_ordinal: i64,
+    pub attempts: Option<i64>,
+}
+
+
+/// The range of dates (both inclusive) over which statistics are computed.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct StatsWindow {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+impl StatsWindow {
+    pub fn is_all_time(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.map(|f| f <= date).unwrap_or(true)
+            && self.to.map(|t| date <= t).unwrap_or(true)
+    }
+
+    /// Obtains the window from the query parameters.
+    ///
+    /// `range=last-30-days` and `range=this-year` select the respective period up to `today`,
+    /// `month=YYYY-MM` selects a calendar month; otherwise, `from` and `to` (`YYYY-MM-DD`, both
+    /// optional) are used.
+    pub fn from_query(query_pairs: &HashMap<Cow<'_, str>, Cow<'_, str>>, today: NaiveDate) -> Result<Self, String> {
+        if let Some(range) = query_pairs.get("range") {
+            return match range.as_ref() {
+                "last-30-days" => Ok(Self {
+                    from: Some(today - Duration::days(29)),
+                    to: Some(today),
+                }),
+                "this-year" => Ok(Self {
+                    from: NaiveDate::from_ymd_opt(today.year(), 1, 1),
+                    to: Some(today),
+                }),
+                other => Err(format!("unknown range {:?}", other)),
+            };
+        }
+
+        if let Some(month_string) = query_pairs.get("month") {
+            let first_day = NaiveDate::parse_from_str(&format!("{}-01", month_string), "%Y-%m-%d")
+                .map_err(|_| format!("invalid month {:?}; expected YYYY-MM", month_string))?;
+            return Ok(Self {
+                from: Some(first_day),
+                to: Some(next_month(first_day) - Duration::days(1)),
+            });
+        }
+
+        let parse_date = |key: &str| -> Result<Option<NaiveDate>, String> {
+            match query_pairs.get(key) {
+                None => Ok(None),
+                Some(s) if s.is_empty() => Ok(None),
+                Some(s) => NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                    .map(Some)
+                    .map_err(|_| format!("invalid {} date {:?}; expected YYYY-MM-DD", key, s)),
+            }
+        };
+        let from = parse_date("from")?;
+        let to = parse_date("to")?;
+        if let (Some(f), Some(t)) = (from, to) {
+            if f > t {
+                return Err("the from date is later than the to date".to_owned());
+            }
+        }
+        Ok(Self { from, to })
+    }
+}
+
+
+/// Returns the first day of the month following the month of the given first day of a month.
+fn next_month(first_day: NaiveDate) -> NaiveDate {
+    if first_day.month() == 12 {
+        NaiveDate::from_ymd_opt(first_day.year() + 1, 1, 1).unwrap()
+    } else {
+        NaiveDate::from_ymd_opt(first_day.year(), first_day.month() + 1, 1).unwrap()
+    }
+}
+
+pub(crate) fn first_day_of_month(date: NaiveDate) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap()
+}
+
+
+/// Whether a puzzle counts as won: it must have been solved, and within the maximum number of
+/// attempts of its site if that is known.
+pub(crate) fn is_victory(attempts: Option<i64>, max_attempts: Option<i64>) -> bool {
+    match (attempts, max_attempts) {
+        (None, _) => false,
+        (Some(_), None) => true,
+        (Some(a), Some(m)) => a <= m,
+    }
+}
+
+/// The winning streaks of a site.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub(crate) struct Streaks {
+    /// The length of the longest winning streak.
+    pub longest: i64,
+
+    /// The length of the winning streak that is still ongoing on the reference date, or 0.
+    pub current: i64,
+
+    /// If the current streak has not been extended on the reference date, the last date on which
+    /// it can still be extended; `None` if it has been extended on the reference date or if there
+    /// is no current streak.
+    pub current_deadline: Option<NaiveDate>,
+}
+
+/// Computes the winning streaks of the given outcomes, which must belong to the same site and be
+/// ordered by date and daily ordinal.
+///
+/// A streak is broken by a defeat and, if `max_gap_days` is given, by two consecutive puzzles
+/// being more than that many days apart. The current streak is the one that has either been
+/// extended on `reference_date` (usually today) or can still be extended without exceeding
+/// `max_gap_days`.
+pub(crate) fn compute_streaks(
+    outcomes: &[&PuzzleOutcome],
+    max_attempts: Option<i64>,
+    max_gap_days: Option<i64>,
+    reference_date: NaiveDate,
+) -> Streaks {
+    let exceeds_gap = |earlier: NaiveDate, later: NaiveDate| match max_gap_days {
+        Some(gap) => (later - earlier).num_days() > gap,
+        None => false,
+    };
+
+    let mut longest = 0;
+    let mut running = 0;
+    let mut previous_date: Option<NaiveDate> = None;
+    for outcome in outcomes {
+        if let Some(prev) = previous_date {
+            if exceeds_gap(prev, outcome.date) {
+                running = 0;
+            }
+        }
+        if is_victory(outcome.attempts, max_attempts) {
+            running += 1;
+            if longest < running {
+                longest = running;
+            }
+        } else {
+            running = 0;
+        }
+        previous_date = Some(outcome.date);
+    }
+
+    let last_date = match previous_date {
+        Some(d) if running > 0 => d,
+        _ => return Streaks { longest, current: 0, current_deadline: None },
+    };
+    if last_date >= reference_date {
+        Streaks { longest, current: running, current_deadline: None }
+    } else if exceeds_gap(last_date, reference_date) {
+        Streaks { longest, current: 0, current_deadline: None }
+    } else {
+        Streaks {
+            longest,
+            current: running,
+            current_deadline: max_gap_days.map(|gap| last_date + Duration::days(gap)),
+        }
+    }
+}
+
+
+/// Accumulates the statistics of one subject.
+#[derive(Default)]
+struct Accumulator {
+    won: i64,
+    lost: i64,
+    attempts_sum: i64,
+    fraction_sum: f64,
+    fraction_count: i64,
+    attempt_counts: BTreeMap<i64, i64>,
+    longest_streak: i64,
+}
+impl Accumulator {
+    fn add(&mut self, outcome: &PuzzleOutcome, max_attempts: Option<i64>) {
+        if !is_victory(outcome.attempts, max_attempts) {
+            self.lost += 1;
+            return;
+        }
+        let attempts = outcome.attempts.unwrap();
+        self.won += 1;
+        self.attempts_sum += attempts;
+        if let Some(m) = max_attempts {
+            self.fraction_sum += (attempts as f64) / (m as f64);
+            self.fraction_count += 1;
+        }
+        *self.attempt_counts.entry(attempts).or_insert(0) += 1;
+    }
+
+    fn into_stats(self, subject: StatsSubject, current_streak: Option<i64>) -> Stats {
+        Stats {
+            subject,
+            puzzles_won: self.won,
+            puzzles_lost: self.lost,
+            average_attempts: if self.won > 0 {
+                Some((self.attempts_sum as f64) / (self.won as f64))
+            } else {
+                None
+            },
+            average_attempts_fraction: if self.fraction_count > 0 {
+                Some(self.fraction_sum / (self.fraction_count as f64))
+            } else {
+                None
+            },
+            longest_streak: self.longest_streak,
+            current_streak,
+            attempt_counts: self.attempt_counts.into_iter()
+                .map(|(attempts, count)| AttemptCount { attempts, count })
+                .collect(),
+            current_streak_deadline: None,
+            monthly_average_attempts: Vec::new(),
+        }
+    }
+}
+
+
+/// Computes the global, per-variant and per-site statistics of the given outcomes, which are
+/// expected to belong to a single player, in a single pass over the outcomes of each site. Current
+/// streaks are determined as of `reference_date`.
+///
+/// The global statistics come first, followed by those of each variant (ordered by name) and those
+/// of each site (in the order given, which is expected to be the display order).
+pub(crate) fn compute_stats(sites: &[PuzzleSite], outcomes: &[PuzzleOutcome], reference_date: NaiveDate) -> Vec<Stats> {
+    let mut site_to_outcomes: HashMap<i64, Vec<&PuzzleOutcome>> = HashMap::new();
+    for outcome in outcomes {
+        site_to_outcomes.entry(outcome.site_id)
+            .or_default()
+            .push(outcome);
+    }
+    for site_outcomes in site_to_outcomes.values_mut() {
+        site_outcomes.sort_unstable_by_key(|o| (o.date, o.day_ordinal));
+    }
+
+    let mut global = Accumulator::default();
+    let mut variant_to_acc: BTreeMap<&str, Accumulator> = BTreeMap::new();
+    let mut site_stats = Vec::with_capacity(sites.len());
+    for site in sites {
+        let variant_acc = variant_to_acc.entry(&site.variant).or_default();
+        let mut site_acc = Accumulator::default();
+
+        let site_outcomes = site_to_outcomes.get(&site.id)
+            .map(|so| so.as_slice())
+            .unwrap_or(&[]);
+        for outcome in site_outcomes {
+            global.add(outcome, site.max_attempts);
+            variant_acc.add(outcome, site.max_attempts);
+            site_acc.add(outcome, site.max_attempts);
+        }
+
+        let streaks = compute_streaks(site_outcomes, site.max_attempts, site.streak_max_gap_days, reference_date);
+        global.longest_streak = global.longest_streak.max(streaks.longest);
+        variant_acc.longest_streak = variant_acc.longest_streak.max(streaks.longest);
+        site_acc.longest_streak = streaks.longest;
+
+        let mut stats = site_acc.into_stats(
+            StatsSubject::Site { id: site.id, name: site.name.clone(), css_class: site.css_class.clone() },
+            Some(streaks.current),
+        );
+        stats.current_streak_deadline = streaks.current_deadline;
+        stats.monthly_average_attempts = monthly_average_attempts(site_outcomes, site.max_attempts);
+        site_stats.push(stats);
+    }
+
+    let mut all_stats = Vec::with_capacity(1 + variant_to_acc.len() + site_stats.len());
+    all_stats.push(global.into_stats(StatsSubject::Global, None));
+    for (variant, acc) in variant_to_acc {
+        all_stats.push(acc.into_stats(StatsSubject::Variant(variant.to_owned()), None));
+    }
+    all_stats.extend(site_stats);
+    all_stats
+}
+
+
+/// Returns the average number of attempts of the won puzzles of each month between the first and
+/// the last of the given outcomes, which must belong to the same site.
+pub(crate) fn monthly_average_attempts(outcomes: &[&PuzzleOutcome], max_attempts: Option<i64>) -> Vec<MonthlyAverage> {
+    let first_month = match outcomes.iter().map(|o| o.date).min() {
+        Some(d) => first_day_of_month(d),
+        None => return Vec::new(),
+    };
+    let last_month = first_day_of_month(outcomes.iter().map(|o| o.date).max().unwrap());
+
+    let mut month_to_sum_count: BTreeMap<NaiveDate, (i64, i64)> = BTreeMap::new();
+    for outcome in outcomes {
+        if !is_victory(outcome.attempts, max_attempts) {
+            continue;
+        }
+        let sum_count = month_to_sum_count.entry(first_day_of_month(outcome.date))
+            .or_insert((0, 0));
+        sum_count.0 += outcome.attempts.unwrap();
+        sum_count.1 += 1;
+    }
+
+    let mut averages = Vec::new();
+    let mut month = first_month;
+    while month <= last_month {
+        let average_attempts = month_to_sum_count.get(&month)
+            .map(|(sum, count)| (*sum as f64) / (*count as f64));
+        averages.push(MonthlyAverage { month, average_attempts });
+        month = next_month(month);
+    }
+    averages
+}
+
+/// Returns the dates between the first and the last of the given dates, which must be ordered, that
+/// are not among the given dates.
+pub(crate) fn missing_dates(dates: &[NaiveDate]) -> Vec<NaiveDate> {
+    let mut missing = Vec::new();
+    for pair in dates.windows(2) {
+        let mut date = pair[0] + Duration::days(1);
+        while date < pair[1] {
+            missing.push(date);
+            date += Duration::days(1);
+        }
+    }
+    missing
+}
+
+
+/// The maximum number of windows whose statistics are kept in the cache.
+const MAX_CACHED_WINDOWS: usize = 64;
+
+/// The sites and puzzle outcomes at a given statistics revision, along with the statistics
+/// already computed from them.
+struct StatsCache {
+    revision: i64,
+    data: Arc<StatsData>,
+    window_to_stats: HashMap<(i64, StatsWindow, NaiveDate), Vec<Stats>>,
+}
+
+struct StatsData {
+    sites: Vec<PuzzleSite>,
+    outcomes: Vec<PuzzleOutcome>,
+}
+
+/// The statistics cache. It is invalidated by comparing its revision to the statistics revision in
+/// the database, which is incremented by triggers whenever puzzles or sites are modified; this
+/// also catches modifications made by other processes such as the command-line tools.
+static STATS_CACHE: Mutex<Option<StatsCache>> = Mutex::new(None);
+
+
+/// Loads the statistics of the given player for the given window. Current streaks are determined
+/// as of the end of the window or `today`, whichever is earlier.
+///
+/// The statistics are computed from the outcomes of all puzzles, which are read from the database
+/// in one go and cached along with the results until a puzzle or site is modified.
+pub(crate) async fn load_stats(db_conn: &DbConnection, player_id: i64, window: &StatsWindow, today: NaiveDate) -> Option<Vec<Stats>> {
+    let reference_date = window.to
+        .map(|to| to.min(today))
+        .unwrap_or(today);
+    let key = (player_id, *window, reference_date);
+
+    // the revision is obtained before the data, so the data is at least as recent as the revision
+    let revision = db_conn.get_stats_revision().await?;
+    let cached_data = {
+        let cache_guard = STATS_CACHE.lock().unwrap();
+        match cache_guard.as_ref() {
+            Some(cache) if cache.revision == revision => {
+                if let Some(stats) = cache.window_to_stats.get(&key) {
+                    return Some(stats.clone());
+                }
+                Some(Arc::clone(&cache.data))
+            },
+            _ => None,
+        }
+    };
+    let data = match cached_data {
+        Some(d) => d,
+        None => {
+            let sites = db_conn.get_sites().await?;
+            let outcomes = db_conn.get_puzzle_outcomes(&StatsWindow::default(), None).await?;
+            Arc::new(StatsData { sites, outcomes })
+        },
+    };
+
+    let window_outcomes: Vec<PuzzleOutcome> = data.outcomes.iter()
+        .filter(|o| o.player_id == player_id && window.contains(o.date))
+        .copied()
+        .collect();
+    let stats = compute_stats(&data.sites, &window_outcomes, reference_date);
+
+    {
+        let mut cache_guard = STATS_CACHE.lock().unwrap();
+        let cache_is_older = cache_guard.as_ref()
+            .map(|c| c.revision < revision)
+            .unwrap_or(true);
+        if cache_is_older {
+            *cache_guard = Some(StatsCache {
+                revision,
+                data,
+                window_to_stats: HashMap::new(),
+            });
+        }
+        if let Some(cache) = cache_guard.as_mut() {
+            if cache.revision == revision {
+                if cache.window_to_stats.len() >= MAX_CACHED_WINDOWS {
+                    cache.window_to_stats.clear();
+                }
+                cache.window_to_stats.insert(key, stats.clone());
+            }
+        }
+    }
+
+    Some(stats)
+}
+
+
+/// A small line chart.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Sparkline {
+    pub width: f64,
+    pub height: f64,
+    pub points: Vec<SparklinePoint>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SparklinePoint {
+    pub x: f64,
+    pub y: f64,
+    pub month: NaiveDate,
+    pub value: f64,
+}
+
+impl Sparkline {
+    /// The margin kept free around the points so that they are not cut off.
+    const MARGIN: f64 = 2.0;
+
+    /// Lays out the monthly averages as a line chart of the given size, with the months along the
+    /// x axis and more attempts further up. Months without an average are skipped but keep their
+    /// place on the x axis. Returns `None` if there are no averages at all.
+    pub fn new(monthly: &[MonthlyAverage], width: f64, height: f64) -> Option<Self> {
+        let values: Vec<f64> = monthly.iter()
+            .filter_map(|m| m.average_attempts)
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        let min_value = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max_value = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        let inner_width = width - 2.0 * Self::MARGIN;
+        let inner_height = height - 2.0 * Self::MARGIN;
+        let x_step = if monthly.len() > 1 {
+            inner_width / ((monthly.len() - 1) as f64)
+        } else {
+            0.0
+        };
+
+        let mut points = Vec::with_capacity(values.len());
+        for (i, m) in monthly.iter().enumerate() {
+            let value = match m.average_attempts {
+                Some(v) => v,
+                None => continue,
+            };
+            let x = if monthly.len() > 1 {
+                Self::MARGIN + (i as f64) * x_step
+            } else {
+                width / 2.0
+            };
+            let y = if max_value > min_value {
+                Self::MARGIN + inner_height * (max_value - value) / (max_value - min_value)
+            } else {
+                height / 2.0
+            };
+            points.push(SparklinePoint { x, y, month: m.month, value });
+        }
+
+        Some(Self { width, height, points })
+    }
+
+    /// Returns the points in the format expected by the `points` attribute of an SVG `polyline`.
+    pub fn polyline_points(&self) -> String {
+        let mut ret = String::new();
+        for point in &self.points {
+            if !ret.is_empty() {
+                ret.push(' ');
+            }
+            write!(ret, "{:.1},{:.1}", point.x, point.y).unwrap();
+        }
+        ret
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    fn outcome(day: u32, attempts: Option<i64>) -> PuzzleOutcome {
+        PuzzleOutcome { player_id: 1, site_id: 1, date: date(day), day_ordinal: 0, attempts }
+    }
+
+    fn streaks_of(outcomes: &[PuzzleOutcome], max_gap_days: Option<i64>, reference_day: u32) -> Streaks {
+        let outcome_refs: Vec<&PuzzleOutcome> = outcomes.iter().collect();
+        compute_streaks(&outcome_refs, Some(6), max_gap_days, date(reference_day))
+    }
+
+    #[test]
+    fn test_defeat_breaks_streak() {
+        let outcomes = [
+            outcome(1, Some(3)), outcome(2, Some(4)), outcome(3, None),
+            outcome(4, Some(2)), outcome(5, Some(5)),
+        ];
+        let streaks = streaks_of(&outcomes, Some(1), 5);
+        assert_eq!(streaks, Streaks { longest: 2, current: 2, current_deadline: None });
+    }
+
+    #[test]
+    fn test_too_many_attempts_break_streak() {
+        let outcomes = [outcome(1, Some(3)), outcome(2, Some(7)), outcome(3, Some(2))];
+        let streaks = streaks_of(&outcomes, Some(1), 3);
+        assert_eq!(streaks, Streaks { longest: 1, current: 1, current_deadline: None });
+    }
+
+    #[test]
+    fn test_missing_day_breaks_streak() {
+        let outcomes = [
+            outcome(1, Some(3)), outcome(2, Some(4)), outcome(3, Some(4)),
+            outcome(5, Some(2)), outcome(6, Some(5)),
+        ];
+        let streaks = streaks_of(&outcomes, Some(1), 6);
+        assert_eq!(streaks, Streaks { longest: 3, current: 2, current_deadline: None });
+    }
+
+    #[test]
+    fn test_missing_day_without_gap_limit() {
+        let outcomes = [
+            outcome(1, Some(3)), outcome(2, Some(4)), outcome(3, Some(4)),
+            outcome(5, Some(2)), outcome(6, Some(5)),
+        ];
+        let streaks = streaks_of(&outcomes, None, 20);
+        assert_eq!(streaks, Streaks { longest: 5, current: 5, current_deadline: None });
+    }
+
+    #[test]
+    fn test_weekly_gap() {
+        let outcomes = [outcome(1, Some(3)), outcome(8, Some(4)), outcome(16, Some(2))];
+        let streaks = streaks_of(&outcomes, Some(7), 16);
+        assert_eq!(streaks, Streaks { longest: 2, current: 1, current_deadline: None });
+    }
+
+    #[test]
+    fn test_multiple_puzzles_per_day() {
+        let mut outcomes = vec![outcome(1, Some(3)), outcome(2, Some(4)), outcome(2, Some(1))];
+        outcomes[2].day_ordinal = 1;
+        let streaks = streaks_of(&outcomes, Some(1), 2);
+        assert_eq!(streaks, Streaks { longest: 3, current: 3, current_deadline: None });
+    }
+
+    #[test]
+    fn test_streak_ended_yesterday_is_pending() {
+        let outcomes = [outcome(1, Some(3)), outcome(2, Some(4))];
+        let streaks = streaks_of(&outcomes, Some(1), 3);
+        assert_eq!(streaks, Streaks { longest: 2, current: 2, current_deadline: Some(date(3)) });
+    }
+
+    #[test]
+    fn test_streak_ended_before_yesterday_is_broken() {
+        let outcomes = [outcome(1, Some(3)), outcome(2, Some(4))];
+        let streaks = streaks_of(&outcomes, Some(1), 4);
+        assert_eq!(streaks, Streaks { longest: 2, current: 0, current_deadline: None });
+    }
+
+    #[test]
+    fn test_defeat_today_has_no_current_streak() {
+        let outcomes = [outcome(1, Some(3)), outcome(2, None)];
+        let streaks = streaks_of(&outcomes, Some(1), 2);
+        assert_eq!(streaks, Streaks { longest: 1, current: 0, current_deadline: None });
+    }
+
+    #[test]
+    fn test_no_outcomes() {
+        let streaks = streaks_of(&[], Some(1), 2);
+        assert_eq!(streaks, Streaks::default());
+    }
+
+    fn site(id: i64, variant: &str, max_attempts: Option<i64>) -> PuzzleSite {
+        PuzzleSite {
+            id,
+            name: format!("Site {}", id),
+            url: format!("https://example.com/{}", id),
+            css_class: format!("site-{}", id),
+            variant: variant.to_owned(),
+            notes: String::new(),
+            available: true,
+            max_attempts,
+            streak_max_gap_days: Some(1),
+        }
+    }
+
+    #[test]
+    fn test_compute_stats_aggregates() {
+        let sites = [site(1, "wordle", Some(6)), site(2, "wordle", None), site(3, "globle", None)];
+        let mut outcomes = vec![
+            outcome(1, Some(3)), outcome(2, Some(7)), outcome(3, None),
+            outcome(1, Some(5)), outcome(2, Some(10)),
+        ];
+        outcomes[3].site_id = 2;
+        outcomes[4].site_id = 3;
+
+        let all_stats = compute_stats(&sites, &outcomes, date(3));
+        let subjects: Vec<&StatsSubject> = all_stats.iter().map(|s| &s.subject).collect();
+        assert_eq!(subjects, [
+            &StatsSubject::Global,
+            &StatsSubject::Variant("globle".to_owned()),
+            &StatsSubject::Variant("wordle".to_owned()),
+            &StatsSubject::Site { id: 1, name: "Site 1".to_owned(), css_class: "site-1".to_owned() },
+            &StatsSubject::Site { id: 2, name: "Site 2".to_owned(), css_class: "site-2".to_owned() },
+            &StatsSubject::Site { id: 3, name: "Site 3".to_owned(), css_class: "site-3".to_owned() },
+        ]);
+
+        let global = &all_stats[0];
+        assert_eq!((global.puzzles_won, global.puzzles_lost), (3, 2));
+        assert_eq!(global.average_attempts, Some(6.0));
+        assert_eq!(global.average_attempts_fraction, Some(0.5));
+        assert_eq!(global.longest_streak, 1);
+        assert_eq!(global.current_streak, None);
+
+        let wordle = &all_stats[2];
+        assert_eq!((wordle.puzzles_won, wordle.puzzles_lost), (2, 2));
+        assert_eq!(wordle.average_attempts, Some(4.0));
+
+        let site_1 = &all_stats[3];
+        assert_eq!((site_1.puzzles_won, site_1.puzzles_lost), (1, 2));
+        assert_eq!(site_1.current_streak, Some(0));
+        assert_eq!(
+            site_1.attempt_counts,
+            [AttemptCount { attempts: 3, count: 1 }],
+        );
+    }
+
+    #[test]
+    fn test_no_missing_dates() {
+        assert_eq!(missing_dates(&[]), []);
+        assert_eq!(missing_dates(&[date(5)]), []);
+        assert_eq!(missing_dates(&[date(5), date(6), date(7)]), []);
+    }
+
+    #[test]
+    fn test_missing_dates_at_start_and_end() {
+        // only dates between the first and the last recorded date are missing
+        assert_eq!(
+            missing_dates(&[date(1), date(4), date(5), date(8)]),
+            [date(2), date(3), date(6), date(7)],
+        );
+    }
+
+    #[test]
+    fn test_missing_dates_with_multiple_ordinals() {
+        // dates with multiple puzzles are contained multiple times
+        assert_eq!(
+            missing_dates(&[date(1), date(1), date(3), date(3), date(3), date(4)]),
+            [date(2)],
+        );
+    }
+
+    fn assert_close(computed: Option<f64>, expected: Option<f64>, what: &str, subject: &StatsSubject) {
+        match (computed, expected) {
+            (Some(c), Some(e)) => assert!((c - e).abs() < 1e-9, "{} of {:?}: {} != {}", what, subject, c, e),
+            (c, e) => assert_eq!(c, e, "{} of {:?}", what, subject),
+        }
+    }
+
+    /// Converts a row of one of the statistics views into statistics, starting at the column
+    /// `puzzles_won`. The current streak is only taken over for sites.
+    fn view_row_to_stats(row: &tokio_postgres::Row, offset: usize, subject: StatsSubject, attempt_counts: Vec<AttemptCount>) -> Stats {
+        let current_streak = if matches!(subject, StatsSubject::Site { .. }) {
+            Some(row.get(offset + 5))
+        } else {
+            None
+        };
+        Stats {
+            subject,
+            puzzles_won: row.get(offset),
+            puzzles_lost: row.get(offset + 1),
+            average_attempts: row.get(offset + 2),
+            average_attempts_fraction: row.get(offset + 3),
+            longest_streak: row.get(offset + 4),
+            current_streak,
+            attempt_counts,
+            current_streak_deadline: None,
+            monthly_average_attempts: Vec::new(),
+        }
+    }
+
+    /// Returns the statistics as computed by the database views, which mix the puzzles of all
+    /// players.
+    async fn get_view_stats(client: &tokio_postgres::Client) -> Vec<Stats> {
+        // number of puzzles won with each number of attempts, per site
+        let attempt_rows = client.query(
+            "
+                SELECT
+                    s.id, s.variant, p.attempts, CAST(COUNT(*) AS bigint)
+                FROM
+                    wordle_archive.sites s
+                    INNER JOIN wordle_archive.puzzles p
+                        ON p.site_id = s.id
+                WHERE
+                    wordle_archive.puzzle_victory(p.attempts, s.max_attempts)
+                GROUP BY
+                    s.id, s.variant, p.attempts
+            ",
+            &[],
+        ).await.expect("failed to obtain attempt counts");
+        let site_attempt_counts: Vec<(i64, String, AttemptCount)> = attempt_rows.iter()
+            .map(|row| (row.get(0), row.get(1), AttemptCount { attempts: row.get(2), count: row.get(3) }))
+            .collect();
+        let sum_attempt_counts = |filter: &dyn Fn(i64, &str) -> bool| -> Vec<AttemptCount> {
+            let mut sums: BTreeMap<i64, i64> = BTreeMap::new();
+            for (site_id, variant, attempt_count) in &site_attempt_counts {
+                if filter(*site_id, variant) {
+                    *sums.entry(attempt_count.attempts).or_insert(0) += attempt_count.count;
+                }
+            }
+            sums.into_iter()
+                .map(|(attempts, count)| AttemptCount { attempts, count })
+                .collect()
+        };
+
+        let mut all_stats = Vec::new();
+
+        let global_row = client.query_one(
+            "
+                SELECT
+                    puzzles_won, puzzles_lost, average_attempts, average_attempts_fraction,
+                    longest_streak
+                FROM
+                    wordle_archive.global_stats
+            ",
+            &[],
+        ).await.expect("failed to obtain global statistics");
+        let attempt_counts = sum_attempt_counts(&|_site_id, _variant| true);
+        all_stats.push(view_row_to_stats(&global_row, 0, StatsSubject::Global, attempt_counts));
+
+        let variant_rows = client.query(
+            "
+                SELECT
+                    variant,
+                    puzzles_won, puzzles_lost, average_attempts, average_attempts_fraction,
+                    longest_streak
+                FROM
+                    wordle_archive.variant_stats
+                ORDER BY
+                    variant
+            ",
+            &[],
+        ).await.expect("failed to obtain variant statistics");
+        for row in &variant_rows {
+            let variant: String = row.get(0);
+            let attempt_counts = sum_attempt_counts(&|_site_id, v| v == variant);
+            all_stats.push(view_row_to_stats(row, 1, StatsSubject::Variant(variant), attempt_counts));
+        }
+
+        let site_rows = client.query(
+            "
+                SELECT
+                    site_id, site_name, site_css_class,
+                    puzzles_won, puzzles_lost, average_attempts, average_attempts_fraction,
+                    longest_streak, current_streak
+                FROM
+                    wordle_archive.site_stats
+                ORDER BY
+                    ordering, site_id
+            ",
+            &[],
+        ).await.expect("failed to obtain site statistics");
+        for row in &site_rows {
+            let id: i64 = row.get(0);
+            let subject = StatsSubject::Site { id, name: row.get(1), css_class: row.get(2) };
+            let attempt_counts = sum_attempt_counts(&|sid, _variant| sid == id);
+            all_stats.push(view_row_to_stats(row, 3, subject, attempt_counts));
+        }
+
+        all_stats
+    }
+
+    /// Compares the statistics computed from the puzzle outcomes with those computed by the
+    /// database views. Requires `WORDLE_ARCHIVE_TEST_DB` to contain a connection string to a
+    /// database whose puzzles all belong to the same player, as the views do not distinguish
+    /// between players; run using `cargo test -- --ignored`.
+    #[tokio::test]
+    #[ignore = "requires a database in WORDLE_ARCHIVE_TEST_DB"]
+    async fn test_compute_stats_matches_views() {
+        assert!(crate::database::initialize_test_pool(), "WORDLE_ARCHIVE_TEST_DB not set");
+        let db_conn = DbConnection::get().await.unwrap();
+        let sites = db_conn.get_sites().await.unwrap();
+        let outcomes = db_conn.get_puzzle_outcomes(&StatsWindow::default(), None).await.unwrap();
+        let view_client = crate::database::DB_POOL.get().unwrap().get().await.unwrap();
+        let view_stats = get_view_stats(&view_client).await;
+        assert!(
+            outcomes.iter().all(|o| o.player_id == outcomes[0].player_id),
+            "the test database contains puzzles of multiple players",
+        );
+
+        // only current streaks depend on the reference date; they are compared separately below
+        let today = chrono::Local::now().date_naive();
+        let computed_stats = compute_stats(&sites, &outcomes, today);
+
+        let subject_to_computed: HashMap<&StatsSubject, &Stats> = computed_stats.iter()
+            .map(|s| (&s.subject, s))
+            .collect();
+        assert_eq!(computed_stats.len(), view_stats.len());
+        for expected in &view_stats {
+            let subject = &expected.subject;
+            let computed = subject_to_computed.get(subject)
+                .unwrap_or_else(|| panic!("no computed statistics for {:?}", subject));
+            assert_eq!(computed.puzzles_won, expected.puzzles_won, "puzzles won of {:?}", subject);
+            assert_eq!(computed.puzzles_lost, expected.puzzles_lost, "puzzles lost of {:?}", subject);
+            assert_close(computed.average_attempts, expected.average_attempts, "average attempts", subject);
+            assert_close(computed.average_attempts_fraction, expected.average_attempts_fraction, "average attempts fraction", subject);
+            assert_eq!(computed.longest_streak, expected.longest_streak, "longest streak of {:?}", subject);
+            assert_eq!(computed.attempt_counts, expected.attempt_counts, "attempt counts of {:?}", subject);
+        }
+
+        // the views end the current streak at the last puzzle of the site regardless of how long
+        // ago it was solved; use that date as the reference date
+        for site in &sites {
+            let mut site_outcomes: Vec<&PuzzleOutcome> = outcomes.iter()
+                .filter(|o| o.site_id == site.id)
+                .collect();
+            site_outcomes.sort_unstable_by_key(|o| (o.date, o.day_ordinal));
+            let last_date = site_outcomes.last()
+                .map(|o| o.date)
+                .unwrap_or(today);
+            let streaks = compute_streaks(&site_outcomes, site.max_attempts, site.streak_max_gap_days, last_date);
+
+            let expected = view_stats.iter()
+                .find(|s| matches!(&s.subject, StatsSubject::Site { id, .. } if *id == site.id))
+                .unwrap_or_else(|| panic!("no view statistics for site {:?}", site.name));
+            assert_eq!(Some(streaks.current), expected.current_streak, "current streak of {:?}", expected.subject);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+//! Checking stored puzzles against the current parsers.
+
+
+use crate::db_puzzle_to_puzzle_part;
+use crate::filters::puzzle_string;
+use crate::import::check_guess_count;
+use crate::model::{Puzzle, SiteAndPuzzle};
+use crate::parsers::parse_puzzle_data;
+
+
+/// The result of parsing a stored puzzle again.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub(crate) struct Reparsed {
+    /// The puzzle as it would be stored by the current parser, with the ID of the stored puzzle.
+    pub puzzle: Puzzle,
+
+    /// Whether the stored pattern differs from the one derived by the current parser.
+    pub pattern_differs: bool,
+
+    /// Whether the stored number of attempts differs from the one derived by the current parser.
+    pub attempts_differs: bool,
+
+    /// Whether the stored puzzle lacks the raw pattern, which has been reconstructed.
+    pub raw_pattern_missing: bool,
+}
+impl Reparsed {
+    /// Whether the stored puzzle should be rewritten.
+    pub fn needs_update(&self) -> bool {
+        self.pattern_differs || self.attempts_differs || self.raw_pattern_missing
+    }
+}
+
+
+/// Parses the stored puzzle again from its head, raw pattern (reconstructed from the pattern if
+/// missing) and tail as well as its solution, and compares the outcome to the stored values.
+pub(crate) fn reparse_puzzle(db_puzzle: &SiteAndPuzzle) -> Result<Reparsed, String> {
+    let stored = &db_puzzle.puzzle;
+    check_guess_count(&stored.pattern, &stored.solution)?;
+
+    let puzzle_part = db_puzzle_to_puzzle_part(db_puzzle);
+    let result = puzzle_string(&puzzle_part)
+        .map_err(|e| format!("failed to assemble result: {}", e))?;
+    let puzzle_data = parse_puzzle_data(&db_puzzle.site, &result, &stored.solution)
+        .map_err(|e| e.to_string())?;
+
+    let mut puzzle = puzzle_data.into_puzzle(stored.site_id, stored.player_id, stored.date, stored.day_ordinal);
+    puzzle.id = stored.id;
+
+    Ok(Reparsed {
+        pattern_differs: puzzle.pattern != stored.pattern,
+        attempts_differs: puzzle.attempts != stored.attempts,
+        raw_pattern_missing: stored.raw_pattern.is_none(),
+        puzzle,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::parsers::test_utils::test_site;
+
+    const RAW_PATTERN: &str = "\u{2B1C}\u{1F7E8}\n\u{1F7E9}\u{1F7E9}";
+
+    /// Returns a puzzle stored as the current parser would store it.
+    fn stored_puzzle() -> SiteAndPuzzle {
+        SiteAndPuzzle {
+            site: test_site("wordle", "wordle"),
+            puzzle: Puzzle {
+                id: 42,
+                site_id: 1,
+                player_id: 2,
+                date: NaiveDate::from_ymd_opt(2022, 6, 16).unwrap(),
+                day_ordinal: 1,
+                head: "Test 1 2/6\n\n".to_owned(),
+                tail: String::new(),
+                pattern: "WM\nCC".to_owned(),
+                solution: "AB\nCD".to_owned(),
+                attempts: Some(2),
+                raw_pattern: Some(RAW_PATTERN.to_owned()),
+            },
+        }
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let db_puzzle = stored_puzzle();
+        let reparsed = reparse_puzzle(&db_puzzle).unwrap();
+        assert!(!reparsed.needs_update());
+        assert_eq!(reparsed.puzzle, db_puzzle.puzzle);
+    }
+
+    #[test]
+    fn test_pattern_differs() {
+        let mut db_puzzle = stored_puzzle();
+        db_puzzle.puzzle.pattern = "WW\nCC".to_owned();
+        let reparsed = reparse_puzzle(&db_puzzle).unwrap();
+        assert!(reparsed.pattern_differs);
+        assert!(!reparsed.attempts_differs);
+        assert!(!reparsed.raw_pattern_missing);
+        assert!(reparsed.needs_update());
+        assert_eq!(reparsed.puzzle.pattern, "WM\nCC");
+        assert_eq!(reparsed.puzzle.id, 42);
+    }
+
+    #[test]
+    fn test_attempts_differ() {
+        let mut db_puzzle = stored_puzzle();
+        db_puzzle.puzzle.attempts = Some(3);
+        let reparsed = reparse_puzzle(&db_puzzle).unwrap();
+        assert!(!reparsed.pattern_differs);
+        assert!(reparsed.attempts_differs);
+        assert!(reparsed.needs_update());
+        assert_eq!(reparsed.puzzle.attempts, Some(2));
+    }
+
+    #[test]
+    fn test_raw_pattern_reconstructed() {
+        let mut db_puzzle = stored_puzzle();
+        db_puzzle.puzzle.raw_pattern = None;
+        let reparsed = reparse_puzzle(&db_puzzle).unwrap();
+        assert!(!reparsed.pattern_differs);
+        assert!(!reparsed.attempts_differs);
+        assert!(reparsed.raw_pattern_missing);
+        assert!(reparsed.needs_update());
+        assert_eq!(reparsed.puzzle.raw_pattern.as_deref(), Some(RAW_PATTERN));
+        assert_eq!(reparsed.puzzle.head, "Test 1 2/6\n\n");
+    }
+
+    #[test]
+    fn test_fewer_guesses_than_sub_puzzles() {
+        let mut db_puzzle = stored_puzzle();
+        db_puzzle.puzzle.pattern = "CC\n\nWW\nCC".to_owned();
+        db_puzzle.puzzle.solution = "AB".to_owned();
+        assert_eq!(reparse_puzzle(&db_puzzle), Err("fewer guesses than sub-puzzles".to_owned()));
+    }
+}